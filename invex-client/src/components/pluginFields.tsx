@@ -1,11 +1,19 @@
 import { IconQuestionMark } from "@tabler/icons-react";
-import { FieldParams, FieldValue, Plugin, PluginField } from "../types/plugin";
+import {
+    FieldCondition,
+    FieldParams,
+    FieldValue,
+    Plugin,
+    PluginField,
+} from "../types/plugin";
 import { PasswordField } from "./fields";
 import { DynamicAvatar } from "./icon";
-import { isArray, isBoolean, isNumber, isString } from "lodash";
+import { isArray, isBoolean, isEqual, isNumber, isString } from "lodash";
 import {
     Center,
     Checkbox,
+    ColorInput,
+    FileInput,
     Group,
     Loader,
     MantineSpacing,
@@ -18,16 +26,77 @@ import {
     TextInput,
 } from "@mantine/core";
 import { useTranslation } from "react-i18next";
-import { PluginsMixin, useApi } from "../context/net";
+import { ClientMixin, PluginsMixin, useApi } from "../context/net";
 import { useEffect, useState } from "react";
+import { DateInput, DateTimePicker } from "@mantine/dates";
+import dayjs from "dayjs";
+import { isEmail, isHexColor, isURL } from "validator";
 
 export type FieldSelector = Partial<{
     config: string;
     grant: string;
     invite: string;
     service: string;
+    code: string;
 }>;
 
+export function evaluateCondition(
+    condition: FieldCondition,
+    values: { [key: string]: FieldValue | null }
+): boolean {
+    const get = (key: string) => values[key] ?? null;
+    switch (condition.type) {
+        case "equals":
+            return isEqual(get(condition.key), condition.value);
+        case "not_equals":
+            return !isEqual(get(condition.key), condition.value);
+        case "one_of":
+            return condition.values.some((v) => isEqual(get(condition.key), v));
+        case "present": {
+            const value = get(condition.key);
+            return !(
+                value === null ||
+                ((isString(value) || isArray(value)) && value.length === 0)
+            );
+        }
+        case "all":
+            return condition.conditions.every((c) =>
+                evaluateCondition(c, values)
+            );
+        case "any":
+            return condition.conditions.some((c) =>
+                evaluateCondition(c, values)
+            );
+        case "not":
+            return !evaluateCondition(condition.condition, values);
+    }
+}
+
+function isValidText(
+    field: PluginField,
+    params: Extract<FieldParams, { type: "text" | "text_area" }>,
+    value: string
+): boolean {
+    if (value.length === 0) {
+        return !field.required;
+    }
+    const length = Array.from(value).length;
+    if (params.min_length !== null && length < params.min_length) {
+        return false;
+    }
+    if (params.max_length !== null && length > params.max_length) {
+        return false;
+    }
+    if (params.type === "text" && params.validation) {
+        try {
+            return new RegExp(params.validation, "u").test(value);
+        } catch {
+            return false;
+        }
+    }
+    return true;
+}
+
 function PluginDefinedField(props: {
     field: PluginField;
     pluginDefined: Extract<FieldParams, { type: "plugin_defined" }>;
@@ -70,7 +139,8 @@ function PluginDefinedField(props: {
                         invite_id: props.selector?.invite ?? "",
                         service_id: props.selector?.service ?? "",
                         grant_id: props.selector?.grant ?? "",
-                    }
+                    },
+                    props.selector?.code
                 ).then((r) => setResult(r.or_default(null)));
                 break;
         }
@@ -94,6 +164,84 @@ function PluginDefinedField(props: {
     );
 }
 
+function FileField(props: {
+    field: PluginField;
+    params: Extract<FieldParams, { type: "file" }>;
+    onChange: (value: FieldValue | null, valid: boolean) => void;
+    error: string | null;
+    selector?: FieldSelector;
+}) {
+    const api = useApi(ClientMixin);
+    const { t } = useTranslation();
+    const [file, setFile] = useState<File | null>(null);
+    const [uploading, setUploading] = useState(false);
+    const [uploadError, setUploadError] = useState<string | null>(null);
+
+    return (
+        <FileInput
+            error={uploadError ?? props.error}
+            className="plugin-field file"
+            withAsterisk={props.field.required}
+            label={props.field.label}
+            description={props.field.description ?? undefined}
+            leftSection={
+                uploading ? (
+                    <Loader size="xs" />
+                ) : (
+                    props.field.icon && (
+                        <DynamicAvatar
+                            source={props.field.icon as any}
+                            fallback={IconQuestionMark}
+                            size={24}
+                            variant="transparent"
+                        />
+                    )
+                )
+            }
+            accept={
+                props.params.content_types.length > 0
+                    ? props.params.content_types.join(",")
+                    : undefined
+            }
+            clearable
+            value={file}
+            onChange={(selected) => {
+                setFile(selected);
+                setUploadError(null);
+                if (!selected) {
+                    props.onChange(null, !props.field.required);
+                    return;
+                }
+                if (
+                    props.params.max_size !== null &&
+                    selected.size > props.params.max_size
+                ) {
+                    setUploadError(t("errors.form.invalid"));
+                    props.onChange(null, false);
+                    return;
+                }
+                setUploading(true);
+                api.upload_argument_file(
+                    props.selector?.code ?? "",
+                    props.selector?.service ?? "",
+                    props.selector?.grant ?? "",
+                    props.field.key,
+                    selected
+                )
+                    .then((response) => {
+                        if (response.success) {
+                            props.onChange(response.data.id, true);
+                        } else {
+                            setUploadError(t("errors.form.invalid"));
+                            props.onChange(null, false);
+                        }
+                    })
+                    .finally(() => setUploading(false));
+            }}
+        />
+    );
+}
+
 export function PluginFieldElement({
     field,
     value,
@@ -112,8 +260,9 @@ export function PluginFieldElement({
     selector?: FieldSelector;
 }) {
     switch (field.field.type) {
-        case "text":
-            if (field.field.password) {
+        case "text": {
+            const params = field.field;
+            if (params.password) {
                 return (
                     <PasswordField
                         className="plugin-field text"
@@ -133,21 +282,12 @@ export function PluginFieldElement({
                         }
                         description={field.description ?? undefined}
                         value={isString(value) ? value : ""}
-                        onChange={(event) => {
-                            if (
-                                field.field.type === "text" &&
-                                field.field.validation
-                            ) {
-                                onChange(
-                                    event.target.value,
-                                    new RegExp(field.field.validation).test(
-                                        event.target.value
-                                    )
-                                );
-                            } else {
-                                onChange(event.target.value, true);
-                            }
-                        }}
+                        onChange={(event) =>
+                            onChange(
+                                event.target.value,
+                                isValidText(field, params, event.target.value)
+                            )
+                        }
                     />
                 );
             } else {
@@ -170,31 +310,16 @@ export function PluginFieldElement({
                             )
                         }
                         value={isString(value) ? value : ""}
-                        onChange={(event) => {
-                            if (
-                                field.required &&
-                                event.target.value.length === 0
-                            ) {
-                                onChange(event.target.value, false);
-                                return;
-                            }
-                            if (
-                                field.field.type === "text" &&
-                                field.field.validation
-                            ) {
-                                onChange(
-                                    event.target.value,
-                                    new RegExp(field.field.validation).test(
-                                        event.target.value
-                                    )
-                                );
-                            } else {
-                                onChange(event.target.value, true);
-                            }
-                        }}
+                        onChange={(event) =>
+                            onChange(
+                                event.target.value,
+                                isValidText(field, params, event.target.value)
+                            )
+                        }
                     />
                 );
             }
+        }
         case "number":
             return (
                 <NumberInput
@@ -316,11 +441,115 @@ export function PluginFieldElement({
                     />
                 );
             }
-        case "text_area":
+        case "text_area": {
+            const params = field.field;
+                return (
+                    <Textarea
+                        error={error}
+                        className="plugin-field textarea"
+                        withAsterisk={field.required}
+                        label={field.label}
+                        description={field.description ?? undefined}
+                        placeholder={field.field.placeholder ?? undefined}
+                        leftSection={
+                            field.icon && (
+                                <DynamicAvatar
+                                    source={field.icon as any}
+                                    fallback={IconQuestionMark}
+                                    size={24}
+                                    variant="transparent"
+                                />
+                            )
+                        }
+                        value={isString(value) ? value : ""}
+                        onChange={(event) =>
+                            onChange(
+                                event.target.value,
+                                isValidText(field, params, event.target.value)
+                            )
+                        }
+                        maxRows={params.lines ?? undefined}
+                        maxLength={params.max_length ?? undefined}
+                    />
+                );
+        }
+        case "date":
             return (
-                <Textarea
+                <DateInput
                     error={error}
-                    className="plugin-field textarea"
+                    className="plugin-field date"
+                    withAsterisk={field.required}
+                    label={field.label}
+                    description={field.description ?? undefined}
+                    leftSection={
+                        field.icon && (
+                            <DynamicAvatar
+                                source={field.icon as any}
+                                fallback={IconQuestionMark}
+                                size={24}
+                                variant="transparent"
+                            />
+                        )
+                    }
+                    valueFormat="YYYY-MM-DD"
+                    value={isString(value) ? dayjs(value).toDate() : null}
+                    onChange={(value) =>
+                        onChange(
+                            value ? dayjs(value).format("YYYY-MM-DD") : null,
+                            field.required ? value !== null : true
+                        )
+                    }
+                    minDate={
+                        field.field.min
+                            ? dayjs(field.field.min).toDate()
+                            : undefined
+                    }
+                    maxDate={
+                        field.field.max
+                            ? dayjs(field.field.max).toDate()
+                            : undefined
+                    }
+                />
+            );
+        case "date_time":
+            return (
+                <DateTimePicker
+                    error={error}
+                    className="plugin-field datetime"
+                    withAsterisk={field.required}
+                    label={field.label}
+                    description={field.description ?? undefined}
+                    leftSection={
+                        field.icon && (
+                            <DynamicAvatar
+                                source={field.icon as any}
+                                fallback={IconQuestionMark}
+                                size={24}
+                                variant="transparent"
+                            />
+                        )
+                    }
+                    value={isString(value) ? new Date(value) : null}
+                    onChange={(value) =>
+                        onChange(
+                            value ? value.toISOString() : null,
+                            field.required ? value !== null : true
+                        )
+                    }
+                    minDate={
+                        field.field.min ? new Date(field.field.min) : undefined
+                    }
+                    maxDate={
+                        field.field.max ? new Date(field.field.max) : undefined
+                    }
+                />
+            );
+        case "email":
+            return (
+                <TextInput
+                    type="email"
+                    error={error}
+                    className="plugin-field email"
                     withAsterisk={field.required}
                     label={field.label}
                     description={field.description ?? undefined}
@@ -336,8 +565,84 @@ export function PluginFieldElement({
                         )
                     }
                     value={isString(value) ? value : ""}
-                    onChange={(event) => onChange(event.target.value, true)}
-                    maxRows={field.field.lines ?? undefined}
+                    onChange={(event) =>
+                        onChange(
+                            event.target.value,
+                            event.target.value.length === 0
+                                ? !field.required
+                                : isEmail(event.target.value)
+                        )
+                    }
+                />
+            );
+        case "url": {
+            const schemes =
+                field.field.schemes.length > 0
+                    ? field.field.schemes
+                    : ["http", "https"];
+                return (
+                    <TextInput
+                        type="url"
+                        error={error}
+                        className="plugin-field url"
+                        withAsterisk={field.required}
+                        label={field.label}
+                        description={field.description ?? undefined}
+                        placeholder={field.field.placeholder ?? undefined}
+                        leftSection={
+                            field.icon && (
+                                <DynamicAvatar
+                                    source={field.icon as any}
+                                    fallback={IconQuestionMark}
+                                    size={24}
+                                    variant="transparent"
+                                />
+                            )
+                        }
+                        value={isString(value) ? value : ""}
+                        onChange={(event) =>
+                            onChange(
+                                event.target.value,
+                                event.target.value.length === 0
+                                    ? !field.required
+                                    : isURL(event.target.value, {
+                                          protocols: schemes,
+                                          require_protocol: true,
+                                          require_tld: false,
+                                      })
+                            )
+                        }
+                    />
+                );
+        }
+        case "color":
+            return (
+                <ColorInput
+                    error={error}
+                    className="plugin-field color"
+                    withAsterisk={field.required}
+                    label={field.label}
+                    description={field.description ?? undefined}
+                    format="hex"
+                    value={isString(value) ? value : ""}
+                    onChange={(value) =>
+                        onChange(
+                            value,
+                            value.length === 0
+                                ? !field.required
+                                : isHexColor(value)
+                        )
+                    }
+                />
+            );
+        case "file":
+            return (
+                <FileField
+                    field={field}
+                    params={field.field}
+                    onChange={onChange}
+                    error={error}
+                    selector={selector}
                 />
             );
         case "plugin_defined":
@@ -356,6 +661,62 @@ export function PluginFieldElement({
     }
 }
 
+type FieldFormValues = {
+    [key: string]: { value: FieldValue | null; valid: boolean };
+};
+
+/**
+ * Resolves each field's visibility and requirement from its siblings, marking hidden fields valid
+ * and empty fields valid only when they aren't required.
+ */
+function resolveConditions(fields: PluginField[], value: FieldFormValues) {
+    const effective = fields.reduce(
+        (prev, field) => ({
+            ...prev,
+            [field.key]:
+                value[field.key]?.valid && value[field.key]?.value !== ""
+                    ? value[field.key].value
+                    : field.default,
+        }),
+        {} as { [key: string]: FieldValue | null }
+    );
+    const visible = fields.filter(
+        (field) =>
+            !field.visible_when ||
+            evaluateCondition(field.visible_when, effective)
+    );
+    const resolved = fields.reduce((prev, field) => {
+        const current = value[field.key] ?? { value: null, valid: true };
+        if (!visible.includes(field)) {
+            return { ...prev, [field.key]: { ...current, valid: true } };
+        }
+        if (current.value === null || current.value === "") {
+            return {
+                ...prev,
+                [field.key]: {
+                    ...current,
+                    valid: !(
+                        field.required ||
+                        (field.required_when !== null &&
+                            evaluateCondition(field.required_when, effective))
+                    ),
+                },
+            };
+        }
+        return { ...prev, [field.key]: current };
+    }, {} as FieldFormValues);
+    return {
+        visible: visible.map((field) => ({
+            ...field,
+            required:
+                field.required ||
+                (field.required_when !== null &&
+                    evaluateCondition(field.required_when, effective)),
+        })),
+        resolved,
+    };
+}
+
 export function PluginFieldForm({
     value,
     onChange,
@@ -376,9 +737,24 @@ export function PluginFieldForm({
     gap?: MantineSpacing;
 }) {
     const { t } = useTranslation();
+    const { visible, resolved } = resolveConditions(fields, value);
+
+    useEffect(() => {
+        const known = fields.reduce(
+            (prev, field) =>
+                field.key in value
+                    ? { ...prev, [field.key]: resolved[field.key] }
+                    : prev,
+            {} as FieldFormValues
+        );
+        if (!isEqual({ ...value, ...known }, value)) {
+            onChange({ ...value, ...known });
+        }
+    }, [value, fields]);
+
     return (
         <Stack gap={gap ?? "sm"} className="plugin-field-form">
-            {fields.map((field) => {
+            {visible.map((field) => {
                 const resolvedValue =
                     (value[field.key]?.value ?? null) === ""
                         ? null
@@ -1,4 +1,5 @@
 import { ClientResource, RedeemingInvite } from "../../../types/client";
+import { FileInfo } from "../../../types/files";
 import { InviteRedemption } from "../../../types/invite";
 import { FieldValue } from "../../../types/plugin";
 import { Response } from "../types";
@@ -40,6 +41,27 @@ export function ClientMixin<TBase extends ApiMixinConstructor>(base: TBase) {
             );
         }
 
+        public async upload_argument_file(
+            code: string,
+            service: string,
+            grant: string,
+            field: string,
+            file: File
+        ): Promise<Response<FileInfo>> {
+            const form = new FormData();
+            form.append("file", file);
+            return await this.request<FileInfo>(
+                `/client/redemption/${code}/files/${service}/${grant}/${field}`,
+                {
+                    method: "post",
+                    data: form,
+                    headers: {
+                        "Content-Type": "multipart/form-data",
+                    },
+                }
+            );
+        }
+
         public async redeem_invite(
             code: string,
             data: RedemptionForm,
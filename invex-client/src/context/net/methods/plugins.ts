@@ -5,7 +5,10 @@ import {
     MethodResult,
     Plugin,
     PluginConfig,
-    PluginMeta,
+    PluginPreview,
+    ConfigTestReport,
+    PluginHealth,
+    PluginTaskRun,
     ValidatedForm,
 } from "../../../types/plugin";
 import { Response } from "../types";
@@ -15,10 +18,18 @@ import { isString } from "lodash";
 export function PluginsMixin<TBase extends ApiMixinConstructor>(base: TBase) {
     return class PluginsMixin extends base {
         public async add_plugin_from_file(
-            file: File
+            file: File,
+            approved_hosts?: string[],
+            signature?: File
         ): Promise<Response<Plugin>> {
             const data = new FormData();
             data.append("plugin", file);
+            if (signature) {
+                data.append("signature", signature);
+            }
+            for (const host of approved_hosts ?? []) {
+                data.append("approved_hosts", host);
+            }
             return await this.request<Plugin>("/plugins/add/file", {
                 method: "post",
                 headers: {
@@ -29,7 +40,8 @@ export function PluginsMixin<TBase extends ApiMixinConstructor>(base: TBase) {
         }
 
         public async add_plugin_from_url(
-            url: string
+            url: string,
+            approved_hosts?: string[]
         ): Promise<Response<Plugin>> {
             return await this.request<Plugin>("/plugins/add/url", {
                 method: "post",
@@ -38,16 +50,21 @@ export function PluginsMixin<TBase extends ApiMixinConstructor>(base: TBase) {
                 },
                 data: {
                     url,
+                    approved_hosts,
                 },
             });
         }
 
         public async preview_plugin_from_file(
-            file: File
-        ): Promise<Response<PluginMeta>> {
+            file: File,
+            signature?: File
+        ): Promise<Response<PluginPreview>> {
             const data = new FormData();
             data.append("plugin", file);
-            return await this.request<PluginMeta>("/plugins/preview/file", {
+            if (signature) {
+                data.append("signature", signature);
+            }
+            return await this.request<PluginPreview>("/plugins/preview/file", {
                 method: "post",
                 headers: {
                     "Content-Type": "multipart/form-data",
@@ -58,8 +75,8 @@ export function PluginsMixin<TBase extends ApiMixinConstructor>(base: TBase) {
 
         public async preview_plugin_from_url(
             url: string
-        ): Promise<Response<PluginMeta>> {
-            return await this.request<PluginMeta>("/plugins/preview/url", {
+        ): Promise<Response<PluginPreview>> {
+            return await this.request<PluginPreview>("/plugins/preview/url", {
                 method: "post",
                 headers: {
                     "Content-Type": "application/json",
@@ -90,6 +107,40 @@ export function PluginsMixin<TBase extends ApiMixinConstructor>(base: TBase) {
             });
         }
 
+        public async set_plugin_hosts(
+            id: string,
+            hosts: string[]
+        ): Promise<Response<Plugin>> {
+            return await this.request<Plugin>(`/plugins/${id}/hosts`, {
+                method: "post",
+                data: hosts,
+            });
+        }
+
+        public async check_plugin_updates(): Promise<Response<Plugin[]>> {
+            return await this.request<Plugin[]>("/plugins/updates/check", {
+                method: "post",
+            });
+        }
+
+        public async apply_plugin_update(
+            id: string
+        ): Promise<Response<Plugin>> {
+            return await this.request<Plugin>(`/plugins/${id}/update`, {
+                method: "post",
+            });
+        }
+
+        public async rollback_plugin(
+            id: string,
+            version: string
+        ): Promise<Response<Plugin>> {
+            return await this.request<Plugin>(
+                `/plugins/${id}/versions/${encodeURIComponent(version)}/rollback`,
+                { method: "post" }
+            );
+        }
+
         public async plugin_config_create(
             plugin: string,
             name: string,
@@ -184,12 +235,16 @@ export function PluginsMixin<TBase extends ApiMixinConstructor>(base: TBase) {
         public async call_plugin_method<T extends MethodCall["method"]>(
             plugin: string,
             method: T,
-            options: Omit<Extract<MethodCall, { method: T }>, "method">
+            options: Omit<Extract<MethodCall, { method: T }>, "method">,
+            inviteCode?: string
         ): Promise<MethodResponse<T>> {
             const result = await this.request<MethodResult<T>>(
                 `/plugins/${plugin}/call`,
                 {
                     method: "post",
+                    headers: inviteCode
+                        ? { "X-InvexCode": inviteCode }
+                        : undefined,
                     data: {
                         method,
                         ...options,
@@ -235,5 +290,33 @@ export function PluginsMixin<TBase extends ApiMixinConstructor>(base: TBase) {
                 `/plugins/${plugin}/configs/${config}/validated`
             );
         }
+
+        public async get_plugin_health(
+            plugin: string,
+            refresh?: boolean
+        ): Promise<Response<PluginHealth | null>> {
+            return await this.request<PluginHealth | null>(
+                `/plugins/${plugin}/health`,
+                { params: { refresh: refresh ?? false } }
+            );
+        }
+
+        public async get_plugin_tasks(
+            plugin: string
+        ): Promise<Response<PluginTaskRun[]>> {
+            return await this.request<PluginTaskRun[]>(
+                `/plugins/${plugin}/tasks`
+            );
+        }
+
+        public async test_plugin_config(
+            plugin: string,
+            config: string
+        ): Promise<Response<ConfigTestReport>> {
+            return await this.request<ConfigTestReport>(
+                `/plugins/${plugin}/configs/${config}/test`,
+                { method: "post" }
+            );
+        }
     };
 }
@@ -1,5 +1,5 @@
 import { DbInvite } from "./invite";
-import { GrantResource, Plugin, PluginField } from "./plugin";
+import { GrantResource, HealthCheck, Plugin, PluginField } from "./plugin";
 
 export type RedeemingGrant = {
     plugin: Plugin;
@@ -11,6 +11,7 @@ export type RedeemingGrant = {
     revocable: boolean;
     url: string | null;
     help: string | null;
+    health: HealthCheck | null;
 };
 
 export type RedeemingService = {
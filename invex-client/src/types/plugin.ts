@@ -19,6 +19,26 @@ export type PluginMeta = {
     description: string | null;
     icon: string | null;
     config: PluginField[];
+    allowed_hosts: string[];
+    tasks: ScheduledTask[];
+};
+
+export type TaskSchedule =
+    | { type: "interval"; seconds: number }
+    | { type: "cron"; expression: string };
+
+export type TaskConfig =
+    | { type: "none" }
+    | { type: "all" }
+    | { type: "named"; name: string };
+
+export type ScheduledTask = {
+    key: string;
+    method: string;
+    label: string;
+    schedule: TaskSchedule;
+    config: TaskConfig;
+    description: string | null;
 };
 
 export type FieldParams =
@@ -27,6 +47,8 @@ export type FieldParams =
           placeholder: string | null;
           password: boolean;
           validation: string | null;
+          min_length: number | null;
+          max_length: number | null;
       }
     | {
           type: "number";
@@ -47,6 +69,8 @@ export type FieldParams =
           type: "text_area";
           lines: number | null;
           placeholder: string | null;
+          min_length: number | null;
+          max_length: number | null;
       }
     | {
           type: "plugin_defined";
@@ -59,10 +83,46 @@ export type FieldParams =
               | "unsigned"
               | "boolean"
               | "string_array";
+      }
+    | {
+          type: "date";
+          min: string | null;
+          max: string | null;
+      }
+    | {
+          type: "date_time";
+          min: string | null;
+          max: string | null;
+      }
+    | {
+          type: "email";
+          placeholder: string | null;
+      }
+    | {
+          type: "url";
+          placeholder: string | null;
+          schemes: string[];
+      }
+    | {
+          type: "color";
+      }
+    | {
+          type: "file";
+          content_types: string[];
+          max_size: number | null;
       };
 
 export type FieldValue = number | string | boolean | string[];
 
+export type FieldCondition =
+    | { type: "equals"; key: string; value: FieldValue | null }
+    | { type: "not_equals"; key: string; value: FieldValue | null }
+    | { type: "one_of"; key: string; values: (FieldValue | null)[] }
+    | { type: "present"; key: string }
+    | { type: "all"; conditions: FieldCondition[] }
+    | { type: "any"; conditions: FieldCondition[] }
+    | { type: "not"; condition: FieldCondition };
+
 export type PluginField = {
     key: string;
     label: string;
@@ -71,6 +131,65 @@ export type PluginField = {
     icon: string | null;
     required: boolean;
     default: FieldValue | null;
+    visible_when: FieldCondition | null;
+    required_when: FieldCondition | null;
+};
+
+export type SignatureStatus =
+    | { status: "verified"; publisher: string }
+    | { status: "unsigned" }
+    | { status: "unsigned_warning" };
+
+export type ValidationIssue = {
+    severity: "error" | "warning";
+    path: string;
+    message: string;
+};
+
+export type ValidationReport = {
+    issues: ValidationIssue[];
+};
+
+export type PluginPreview = PluginMeta & {
+    validation: ValidationReport;
+    signature: SignatureStatus;
+};
+
+export type PluginVersion = {
+    version: string;
+    metadata: PluginMeta;
+    url: string | null;
+    installed_at: string;
+};
+
+export type PluginUpdate = {
+    version: string;
+    metadata: PluginMeta;
+    checked_at: string;
+};
+
+export type HealthCheck = {
+    status: "healthy" | "unhealthy";
+    latency_ms: number;
+    error: string | null;
+    checked_at: string;
+};
+
+export type PluginHealth = {
+    plugin: HealthCheck;
+    configs: { [key: string]: HealthCheck };
+};
+
+export type PluginTaskRun = {
+    _id: string;
+    plugin: string;
+    task: string;
+    config: string | null;
+    call_id: string;
+    status: "success" | "failure";
+    message: string | null;
+    started_at: string;
+    finished_at: string;
 };
 
 export type Plugin = {
@@ -78,6 +197,12 @@ export type Plugin = {
     metadata: PluginMeta;
     url: string | null;
     enabled: boolean;
+    approved_hosts: string[];
+    versions: PluginVersion[];
+    update: PluginUpdate | null;
+    signature: SignatureStatus;
+    validation: ValidationReport;
+    health: PluginHealth | null;
 };
 
 export type PluginConfig = {
@@ -91,6 +216,7 @@ export type PluginConfig = {
 export type ValidatedArgument = {
     argument: PluginField;
     valid: boolean;
+    visible: boolean;
     value: FieldValue | null;
     previous: FieldValue | null;
 };
@@ -100,6 +226,19 @@ export type ValidatedForm = {
     arguments: { [key: string]: ValidatedArgument };
 };
 
+export type ConfigTestResult = {
+    success: boolean;
+    message: string | null;
+    fields: { [key: string]: string };
+};
+
+export type ConfigTestReport = {
+    success: boolean;
+    validation: ValidatedForm;
+    live: ConfigTestResult | null;
+    fields: { [key: string]: string[] };
+};
+
 export type MethodCall =
     | {
           method: "plugin_defined_field";
@@ -73,11 +73,15 @@ type RedemptionForm = {
 };
 
 function ServiceItem({
+    code,
+    inviteId,
     service,
     form,
     validation,
     index,
 }: {
+    code: string;
+    inviteId: string;
     service: RedeemingService;
     form: UseFormReturnType<RedemptionForm>;
     validation: Response<InviteRedemption> | null;
@@ -302,6 +306,12 @@ function ServiceItem({
                                         plugin={action.plugin}
                                         fields={action.arguments}
                                         context="invite"
+                                        selector={{
+                                            code,
+                                            invite: inviteId,
+                                            service: service.id,
+                                            grant: id,
+                                        }}
                                         value={Object.entries(
                                             (form.values.services[service.id] ??
                                                 {})[id] ?? {}
@@ -822,6 +832,8 @@ export function RedeemInviteView() {
                             {redeeming.services.map((service, index) => (
                                 <ServiceItem
                                     key={service.id}
+                                    code={redeeming.invite.code}
+                                    inviteId={redeeming.invite._id}
                                     service={service}
                                     form={form}
                                     validation={redeemed}
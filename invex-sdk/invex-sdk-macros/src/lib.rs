@@ -0,0 +1,372 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Expr, Fields, GenericArgument, Ident,
+    LitBool, LitStr, PathArguments, Type,
+};
+
+extern crate proc_macro;
+
+/// Options read from a field's `#[field(...)]` attribute.
+#[derive(Default)]
+struct FieldOptions {
+    key: Option<LitStr>,
+    label: Option<LitStr>,
+    description: Option<LitStr>,
+    icon: Option<LitStr>,
+    placeholder: Option<LitStr>,
+    password: bool,
+    validation: Option<LitStr>,
+    min_length: Option<Expr>,
+    max_length: Option<Expr>,
+    lines: Option<Expr>,
+    min: Option<Expr>,
+    max: Option<Expr>,
+    plugin_defined: Option<LitStr>,
+    context: Option<LitStr>,
+    kind: Option<Expr>,
+    required: Option<LitBool>,
+    default: Option<Expr>,
+    visible_when: Option<Expr>,
+    required_when: Option<Expr>,
+}
+
+impl FieldOptions {
+    fn parse(field: &syn::Field) -> syn::Result<Self> {
+        let mut options = FieldOptions::default();
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("field")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("key") {
+                    options.key = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("label") {
+                    options.label = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("description") {
+                    options.description = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("icon") {
+                    options.icon = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("placeholder") {
+                    options.placeholder = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("password") {
+                    options.password = true;
+                } else if meta.path.is_ident("validation") {
+                    options.validation = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("min_length") {
+                    options.min_length = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("max_length") {
+                    options.max_length = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("lines") {
+                    options.lines = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("min") {
+                    options.min = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("max") {
+                    options.max = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("plugin_defined") {
+                    options.plugin_defined = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("context") {
+                    options.context = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("kind") {
+                    options.kind = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("required") {
+                    options.required = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    options.default = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("visible_when") {
+                    options.visible_when = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("required_when") {
+                    options.required_when = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("Unknown field option"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+/// Returns the inner type if `ty` is `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    if let Type::Path(path) = ty {
+        let segment = path.path.segments.last()?;
+        if segment.ident == "Option" {
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if let Some(GenericArgument::Type(inner)) = args.args.first() {
+                    return Some(inner);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Name of the last path segment of a type, plus its first type argument if it has one.
+fn type_name(ty: &Type) -> Option<(String, Option<String>)> {
+    if let Type::Path(path) = ty {
+        let segment = path.path.segments.last()?;
+        let argument = match &segment.arguments {
+            PathArguments::AngleBracketed(args) => match args.args.first() {
+                Some(GenericArgument::Type(inner)) => type_name(inner).map(|(name, _)| name),
+                _ => None,
+            },
+            _ => None,
+        };
+        Some((segment.ident.to_string(), argument))
+    } else {
+        None
+    }
+}
+
+/// Which `NumberType` (and `ExpectedType`) variant a numeric Rust type maps to.
+fn number_kind(name: &str) -> Option<Ident> {
+    let variant = match name {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" => "Integer",
+        "u8" | "u16" | "u32" | "u64" | "u128" | "usize" => "Unsigned",
+        "f32" | "f64" => "Float",
+        _ => return None,
+    };
+    Some(format_ident!("{}", variant))
+}
+
+fn expected_type(ty: &Type) -> Option<TokenStream2> {
+    let (name, argument) = type_name(ty)?;
+    match (name.as_str(), argument.as_deref()) {
+        ("String", _) => Some(quote!(::invex_sdk::ExpectedType::String)),
+        ("bool", _) => Some(quote!(::invex_sdk::ExpectedType::Boolean)),
+        ("Vec", Some("String")) => Some(quote!(::invex_sdk::ExpectedType::StringArray)),
+        (other, _) => {
+            let variant = number_kind(other)?;
+            Some(quote!(::invex_sdk::ExpectedType::#variant))
+        }
+    }
+}
+
+fn optional_str(value: &Option<LitStr>) -> TokenStream2 {
+    match value {
+        Some(v) => quote!(Some(#v.to_string())),
+        None => quote!(None),
+    }
+}
+
+fn optional_expr(value: &Option<Expr>, cast: TokenStream2) -> TokenStream2 {
+    match value {
+        Some(v) => quote!(Some((#v) as #cast)),
+        None => quote!(None),
+    }
+}
+
+fn optional_condition(value: &Option<Expr>) -> TokenStream2 {
+    match value {
+        Some(v) => quote!(Some(::std::convert::Into::<::invex_sdk::FieldCondition>::into(#v))),
+        None => quote!(None),
+    }
+}
+
+/// Works out the `FieldType` for a field, from an explicit `kind`, a `plugin_defined` method or
+/// the field's Rust type.
+fn field_type(ty: &Type, options: &FieldOptions) -> syn::Result<TokenStream2> {
+    if let Some(kind) = &options.kind {
+        return Ok(quote!(#kind));
+    }
+
+    if let Some(method) = &options.plugin_defined {
+        let context = match options.context.as_ref().map(|c| c.value()).as_deref() {
+            Some("plugin") => quote!(::invex_sdk::PluginDefinedMethodContext::Plugin),
+            Some("service") => quote!(::invex_sdk::PluginDefinedMethodContext::Service),
+            Some("invite") => quote!(::invex_sdk::PluginDefinedMethodContext::Invite),
+            Some("admin") => quote!(::invex_sdk::PluginDefinedMethodContext::Admin),
+            _ => {
+                return Err(syn::Error::new(
+                    method.span(),
+                    "Plugin-defined fields need a context of \"plugin\", \"service\", \"invite\" or \"admin\"",
+                ))
+            }
+        };
+        let expected = expected_type(ty).ok_or(syn::Error::new(
+            ty.span(),
+            "Can't infer the expected type of this plugin-defined field",
+        ))?;
+        return Ok(quote! {
+            ::invex_sdk::FieldType::PluginDefined {
+                method: #method.to_string(),
+                context: #context,
+                expected_type: #expected,
+            }
+        });
+    }
+
+    let placeholder = optional_str(&options.placeholder);
+    let min_length = optional_expr(&options.min_length, quote!(u64));
+    let max_length = optional_expr(&options.max_length, quote!(u64));
+    let (name, _) = type_name(ty).unwrap_or_default();
+    match name.as_str() {
+        "String" if options.lines.is_some() => {
+            let lines = optional_expr(&options.lines, quote!(u64));
+            Ok(quote! {
+                ::invex_sdk::FieldType::TextArea {
+                    lines: #lines,
+                    placeholder: #placeholder,
+                    min_length: #min_length,
+                    max_length: #max_length,
+                }
+            })
+        }
+        "String" => {
+            let password = options.password;
+            let validation = optional_str(&options.validation);
+            Ok(quote! {
+                ::invex_sdk::FieldType::Text {
+                    placeholder: #placeholder,
+                    password: #password,
+                    validation: #validation,
+                    min_length: #min_length,
+                    max_length: #max_length,
+                }
+            })
+        }
+        "bool" => Ok(quote!(::invex_sdk::FieldType::Switch {})),
+        other => match number_kind(other) {
+            Some(variant) => {
+                let min = optional_expr(&options.min, quote!(f64));
+                let max = optional_expr(&options.max, quote!(f64));
+                Ok(quote! {
+                    ::invex_sdk::FieldType::Number {
+                        placeholder: #placeholder,
+                        kind: ::invex_sdk::NumberType::#variant,
+                        min: #min,
+                        max: #max,
+                    }
+                })
+            }
+            None => Err(syn::Error::new(
+                ty.span(),
+                "Can't infer a field type for this type, set one with `kind = ...`",
+            )),
+        },
+    }
+}
+
+/// Derives `invex_sdk::PluginFields`, turning each named field into a `PluginArgument` and reading
+/// the struct back out of a `ParameterMap` under the same keys.
+///
+/// Field types are inferred from the Rust type (`String`, `bool` and numbers), and `Option<T>`
+/// fields are optional. Everything else is set through `#[field(...)]`: `key`, `label`,
+/// `description`, `icon`, `placeholder`, `password`, `validation`, `min_length`, `max_length`,
+/// `lines`, `min`, `max`, `plugin_defined` with `context`, `kind` for any other `FieldType`,
+/// `required`, `default`, and `visible_when` / `required_when` taking a `FieldCondition`.
+#[proc_macro_derive(PluginFields, attributes(field))]
+pub fn derive_plugin_fields(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+    match expand(&ast) {
+        Ok(gen) => gen.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(ast: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+    let fields = match &ast.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    ast.span(),
+                    "PluginFields can only be derived on structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                ast.span(),
+                "PluginFields can only be derived on structs",
+            ))
+        }
+    };
+
+    let mut arguments = Vec::new();
+    let mut values = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let options = FieldOptions::parse(field)?;
+        let key = options
+            .key
+            .as_ref()
+            .map(|k| k.value())
+            .unwrap_or(ident.to_string());
+        let label = options
+            .label
+            .as_ref()
+            .map(|l| l.value())
+            .unwrap_or(ident.to_string());
+        let inner = option_inner(&field.ty);
+        let kind = field_type(inner.unwrap_or(&field.ty), &options)?;
+        let required = options
+            .required
+            .as_ref()
+            .map(|r| r.value)
+            .unwrap_or(inner.is_none());
+        let description = optional_str(&options.description);
+        let icon = optional_str(&options.icon);
+        let default = match &options.default {
+            Some(d) => quote!(::invex_sdk::__private::serde_json::to_value(#d).ok()),
+            None => quote!(None),
+        };
+        if let Some(condition) = &options.visible_when {
+            if inner.is_none() && options.default.is_none() {
+                return Err(syn::Error::new(
+                    condition.span(),
+                    "Conditionally visible fields must be an `Option` or have a `default`",
+                ));
+            }
+        }
+        let visible_when = optional_condition(&options.visible_when);
+        let required_when = optional_condition(&options.required_when);
+
+        arguments.push(quote! {
+            ::invex_sdk::PluginArgument {
+                key: #key.to_string(),
+                label: #label.to_string(),
+                field: #kind,
+                description: #description,
+                icon: #icon,
+                required: #required,
+                default: #default,
+                visible_when: #visible_when,
+                required_when: #required_when,
+            }
+        });
+
+        let value = match (inner, &options.default) {
+            (Some(inner), Some(d)) => quote_spanned! {field.ty.span()=>
+                params.get::<Option<#inner>>(#key)?.flatten().or(Some(::std::convert::Into::into(#d)))
+            },
+            (Some(inner), None) => quote_spanned! {field.ty.span()=>
+                params.get::<Option<#inner>>(#key)?.flatten()
+            },
+            (None, Some(d)) => quote_spanned! {field.ty.span()=>
+                params.get(#key)?.unwrap_or_else(|| ::std::convert::Into::into(#d))
+            },
+            (None, None) => quote_spanned! {field.ty.span()=>
+                params.get(#key)?.ok_or(format!("Missing value for {}", #key))?
+            },
+        };
+        values.push(quote!(#ident: #value));
+    }
+
+    Ok(quote! {
+        impl #impl_generics ::invex_sdk::PluginFields for #name #ty_generics #where_clause {
+            fn fields() -> Vec<::invex_sdk::PluginArgument> {
+                vec![#(#arguments),*]
+            }
+
+            fn from_params(
+                params: &::invex_sdk::params::ParameterMap
+            ) -> Result<Self, Box<dyn ::std::error::Error>> {
+                Ok(Self {
+                    #(#values),*
+                })
+            }
+        }
+    })
+}
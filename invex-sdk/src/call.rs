@@ -1,7 +1,11 @@
+use std::{collections::HashMap, fmt::Display};
+
 use anyhow::Error;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_repr::{Deserialize_repr, Serialize_repr};
 
-use crate::FieldType;
+use crate::{FieldType, GrantResource};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "method", rename_all = "snake_case")]
@@ -17,6 +21,63 @@ pub enum MethodReply {
     FieldDefinition(FieldType)
 }
 
+/// Error codes reported when a plugin call is stopped by one of the server's execution limits.
+#[derive(Serialize_repr, Deserialize_repr, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(i32)]
+pub enum LimitError {
+    Timeout = 1001,
+    Memory = 1002,
+    HttpResponse = 1003,
+    Fuel = 1004
+}
+
+impl Display for LimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LimitError::Timeout => "timeout",
+            LimitError::Memory => "memory",
+            LimitError::HttpResponse => "HTTP response size",
+            LimitError::Fuel => "fuel"
+        })
+    }
+}
+
+/// Optional exports the server calls when an admin changes a plugin's state. Hooks return `Json<()>`,
+/// and returning an error vetoes the change, with the error message shown to the admin.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleHook {
+    Enable,
+    Disable,
+    ConfigSaved,
+    Uninstall
+}
+
+impl LifecycleHook {
+    pub const ALL: [LifecycleHook; 4] = [
+        LifecycleHook::Enable,
+        LifecycleHook::Disable,
+        LifecycleHook::ConfigSaved,
+        LifecycleHook::Uninstall
+    ];
+
+    /// Name of the export implementing this hook.
+    pub fn export(&self) -> &'static str {
+        match self {
+            LifecycleHook::Enable => "on_enable",
+            LifecycleHook::Disable => "on_disable",
+            LifecycleHook::ConfigSaved => "on_config_saved",
+            LifecycleHook::Uninstall => "on_uninstall"
+        }
+    }
+}
+
+impl Display for LifecycleHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.export())
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum MethodResult {
@@ -34,4 +95,69 @@ impl From<(Error, i32)> for MethodResult {
     fn from(value: (Error, i32)) -> Self {
         MethodResult::Failure { code: value.1, reason: value.0.to_string() }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ActionResult {
+    #[serde(default)]
+    pub message: Option<String>,
+
+    #[serde(default)]
+    pub resource: Option<GrantResource>,
+
+    #[serde(default)]
+    pub data: Option<Value>
+}
+
+/// Name of the optional export that checks a configuration profile against the live service.
+pub const TEST_CONFIG_EXPORT: &str = "test_config";
+
+/// Returned by `test_config`. `fields` maps config keys to the problem found with each of them.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ConfigTestResult {
+    pub success: bool,
+
+    #[serde(default)]
+    pub message: Option<String>,
+
+    #[serde(default)]
+    pub fields: HashMap<String, String>
+}
+
+impl ConfigTestResult {
+    pub fn success(message: impl AsRef<str>) -> Self {
+        Self { success: true, message: Some(message.as_ref().to_string()), fields: HashMap::new() }
+    }
+
+    pub fn failure(message: impl AsRef<str>) -> Self {
+        Self { success: false, message: Some(message.as_ref().to_string()), fields: HashMap::new() }
+    }
+
+    pub fn with_field(mut self, key: impl AsRef<str>, problem: impl AsRef<str>) -> Self {
+        self.success = false;
+        self.fields.insert(key.as_ref().to_string(), problem.as_ref().to_string());
+        self
+    }
+}
+
+/// Name of the optional export the server calls periodically to check that a plugin's upstream is reachable.
+pub const HEALTHCHECK_EXPORT: &str = "healthcheck";
+
+/// Returned by `healthcheck`. A call that fails outright is treated as unhealthy as well.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct HealthCheckResult {
+    pub healthy: bool,
+
+    #[serde(default)]
+    pub message: Option<String>
+}
+
+/// Returned by a scheduled task's export. Returning an error marks the run as failed.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TaskResult {
+    #[serde(default)]
+    pub message: Option<String>,
+
+    #[serde(default)]
+    pub data: Option<Value>
+}
@@ -0,0 +1,24 @@
+//! Host functions for storing files on the server. The `_raw` variants pass file contents as plain bytes
+//! rather than JSON, which avoids encoding overhead for large files.
+
+#[cfg(any(target_arch = "wasm32", not(feature = "testing")))]
+pub use host::*;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "testing"))]
+pub use crate::testing::fs::*;
+
+#[cfg(any(target_arch = "wasm32", not(feature = "testing")))]
+mod host {
+    use extism_pdk::*;
+
+    use crate::{PluginFileData, PluginFileMetadata};
+
+    #[host_fn]
+    extern "ExtismHost" {
+        pub fn fs_load(id: String) -> Json<PluginFileData>;
+        pub fn fs_store(data: Json<PluginFileData>) -> String;
+        pub fn fs_info(id: String) -> Json<PluginFileMetadata>;
+        pub fn fs_load_raw(id: String) -> Vec<u8>;
+        pub fn fs_store_raw(metadata: Json<PluginFileMetadata>, data: Vec<u8>) -> String;
+    }
+}
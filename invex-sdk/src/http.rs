@@ -0,0 +1,71 @@
+//! HTTP requests made through the Extism host. Plugins should go through [`request`] rather than
+//! `extism_pdk::http::request`, so that native tests can answer requests with the fakes in `testing`.
+
+use std::collections::HashMap;
+
+use extism_pdk::Error;
+use serde::de::DeserializeOwned;
+
+#[derive(Clone, Debug, Default)]
+pub struct HttpResponse {
+    status: u16,
+    body: Vec<u8>,
+    headers: HashMap<String, String>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self { status, body: body.into(), headers: HashMap::new() }
+    }
+
+    pub fn json_body<T: serde::Serialize>(status: u16, body: &T) -> Self {
+        Self::new(status, serde_json::to_vec(body).unwrap_or_default())
+            .with_header("Content-Type", "application/json")
+    }
+
+    pub fn with_header(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.headers.insert(key.as_ref().to_string(), value.as_ref().to_string());
+        self
+    }
+
+    pub fn status_code(&self) -> u16 {
+        self.status
+    }
+
+    pub fn body(&self) -> Vec<u8> {
+        self.body.clone()
+    }
+
+    pub fn headers(&self) -> &HashMap<String, String> {
+        &self.headers
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+}
+
+#[cfg(any(target_arch = "wasm32", not(feature = "testing")))]
+pub fn request<'a, T: extism_pdk::ToBytes<'a>>(
+    req: &extism_pdk::HttpRequest,
+    body: Option<T>
+) -> Result<HttpResponse, Error> {
+    let response = extism_pdk::http::request::<T>(req, body)?;
+    Ok(HttpResponse {
+        status: response.status_code(),
+        body: response.body(),
+        headers: response.headers().clone(),
+    })
+}
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "testing"))]
+pub fn request<'a, T: extism_pdk::ToBytes<'a>>(
+    req: &extism_pdk::HttpRequest,
+    body: Option<T>
+) -> Result<HttpResponse, Error> {
+    let body = match body {
+        Some(b) => Some(b.to_bytes()?.as_ref().to_vec()),
+        None => None,
+    };
+    crate::testing::handle_request(req, body)
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Namespace a key-value entry lives in. `Plugin` entries are shared by every call of the plugin,
+/// while `Config` entries are only visible to calls made under the same plugin configuration profile.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum KvScope {
+    #[default]
+    Plugin,
+    Config
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KvKey {
+    pub key: String,
+
+    #[serde(default)]
+    pub scope: KvScope
+}
+
+impl KvKey {
+    pub fn plugin<T: AsRef<str>>(key: T) -> Self {
+        Self { key: key.as_ref().to_string(), scope: KvScope::Plugin }
+    }
+
+    pub fn config<T: AsRef<str>>(key: T) -> Self {
+        Self { key: key.as_ref().to_string(), scope: KvScope::Config }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct KvEntry {
+    pub key: String,
+    pub value: Value,
+
+    #[serde(default)]
+    pub scope: KvScope
+}
+
+#[cfg(any(target_arch = "wasm32", not(feature = "testing")))]
+pub use host::*;
+
+#[cfg(all(not(target_arch = "wasm32"), feature = "testing"))]
+pub use crate::testing::kv::*;
+
+#[cfg(any(target_arch = "wasm32", not(feature = "testing")))]
+mod host {
+    use extism_pdk::*;
+    use serde_json::Value;
+
+    use super::{KvEntry, KvKey};
+
+    // kv_get returns the stored value, kv_set replaces any previous value, kv_delete reports
+    // whether the key existed and kv_list returns every entry whose key starts with the given prefix.
+    #[host_fn]
+    extern "ExtismHost" {
+        pub fn kv_get(key: Json<KvKey>) -> Json<Option<Value>>;
+        pub fn kv_set(entry: Json<KvEntry>);
+        pub fn kv_delete(key: Json<KvKey>) -> Json<bool>;
+        pub fn kv_list(prefix: Json<KvKey>) -> Json<Vec<KvEntry>>;
+    }
+}
@@ -1,13 +1,42 @@
-use std::{collections::HashMap, fmt::Debug};
-
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Mutex, OnceLock},
+};
+
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use chrono::{DateTime, FixedOffset, NaiveDate};
 use cryptoxide::{digest::Digest, sha2::Sha512};
 use derive_builder::Builder;
 use extism_pdk::{FromBytes, ToBytes};
+use rand_core::OsRng;
+use regex::Regex;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
+use url::Url;
+
+// Lets `#[derive(PluginFields)]`, which names `::invex_sdk`, be used inside this crate
+extern crate self as invex_sdk;
 
 pub mod params;
 pub mod call;
+pub mod kv;
+pub mod fs;
+pub mod http;
+pub mod random;
+
+#[cfg(all(feature = "testing", not(target_arch = "wasm32")))]
+pub mod testing;
+
+pub use invex_sdk_macros::PluginFields;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(untagged)]
@@ -36,20 +65,15 @@ pub enum ExpectedType {
     StringArray
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum NumberType {
     Integer,
+    #[default]
     Float,
     Unsigned
 }
 
-impl Default for NumberType {
-    fn default() -> Self {
-        NumberType::Float
-    }
-}
-
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum FieldType {
@@ -60,8 +84,15 @@ pub enum FieldType {
         #[serde(default)]
         password: bool,
 
+        /// Regular expression the value must match somewhere; anchor it with `^...$` to match the whole value
         #[serde(default)]
         validation: Option<String>,
+
+        #[serde(default)]
+        min_length: Option<u64>,
+
+        #[serde(default)]
+        max_length: Option<u64>,
     },
     Number {
         #[serde(default)]
@@ -89,12 +120,221 @@ pub enum FieldType {
 
         #[serde(default)]
         placeholder: Option<String>,
+
+        #[serde(default)]
+        min_length: Option<u64>,
+
+        #[serde(default)]
+        max_length: Option<u64>,
     },
     PluginDefined {
         method: String,
         context: PluginDefinedMethodContext,
         expected_type: ExpectedType
     },
+
+    /// A calendar date formatted as `YYYY-MM-DD`, with optional bounds in the same format
+    Date {
+        #[serde(default)]
+        min: Option<String>,
+
+        #[serde(default)]
+        max: Option<String>,
+    },
+
+    /// An RFC 3339 timestamp, with optional bounds in the same format
+    DateTime {
+        #[serde(default)]
+        min: Option<String>,
+
+        #[serde(default)]
+        max: Option<String>,
+    },
+    Email {
+        #[serde(default)]
+        placeholder: Option<String>,
+    },
+
+    /// An absolute URL with a host. If `schemes` is empty, only `http` and `https` are accepted.
+    Url {
+        #[serde(default)]
+        placeholder: Option<String>,
+
+        #[serde(default)]
+        schemes: Vec<String>,
+    },
+
+    /// A hex color, either `#rgb` or `#rrggbb`
+    Color {},
+
+    /// A file uploaded by the redeemer, passed to the plugin as a file ID it can read with `fs_load`.
+    /// An empty `content_types` accepts any type, and entries like `image/*` match a whole family.
+    File {
+        #[serde(default)]
+        content_types: Vec<String>,
+
+        /// Largest accepted upload, in bytes
+        #[serde(default)]
+        max_size: Option<u64>,
+    },
+}
+
+impl FieldType {
+    /// Whether a `File` field accepts an upload of this content type and size. Always false for
+    /// other field types.
+    pub fn accepts_file(&self, content_type: impl AsRef<str>, size: u64) -> bool {
+        if let FieldType::File { content_types, max_size } = self {
+            let content_type = content_type.as_ref().split(';').next().unwrap_or_default().trim();
+            let type_valid = content_types.is_empty() || content_types.iter().any(|accepted| match accepted.strip_suffix("/*") {
+                Some(family) => content_type.split_once('/').is_some_and(|(f, _)| f.eq_ignore_ascii_case(family)),
+                None => accepted.eq_ignore_ascii_case(content_type)
+            });
+            type_valid && max_size.is_none_or(|m| size <= m)
+        } else {
+            false
+        }
+    }
+}
+
+/// Whether a string value is within the length bounds, counted in characters, and matches the
+/// pattern if there is one. An invalid pattern rejects every value.
+fn is_valid_text(value: &Value, pattern: &Option<String>, min_length: Option<u64>, max_length: Option<u64>) -> bool {
+    if let Some(val) = value.as_str() {
+        let length = val.chars().count() as u64;
+        min_length.is_none_or(|m| m <= length)
+            && max_length.is_none_or(|m| m >= length)
+            && pattern.as_ref().is_none_or(|p| compiled_pattern(p).is_some_and(|r| r.is_match(val)))
+    } else {
+        false
+    }
+}
+
+/// Compiles a validation pattern once and reuses it for every later value, caching invalid
+/// patterns as `None` too.
+fn compiled_pattern(pattern: &str) -> Option<Regex> {
+    static PATTERNS: OnceLock<Mutex<HashMap<String, Option<Regex>>>> = OnceLock::new();
+    let mut patterns = PATTERNS.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    patterns
+        .entry(pattern.to_string())
+        .or_insert_with(|| Regex::new(pattern).ok())
+        .clone()
+}
+
+/// Parses a `Date` field value, formatted `YYYY-MM-DD`.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+/// Parses a `DateTime` field value, formatted as RFC 3339.
+pub fn parse_datetime(value: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(value).ok()
+}
+
+/// Whether `value` parses and falls within the optional bounds, which must parse the same way.
+fn within_bounds<T: PartialOrd>(value: &Value, min: &Option<String>, max: &Option<String>, parse: impl Fn(&str) -> Option<T>) -> bool {
+    if let Some(val) = value.as_str().and_then(&parse) {
+        min.as_deref().is_none_or(|m| parse(m).is_some_and(|m| m <= val)) && max.as_deref().is_none_or(|m| parse(m).is_some_and(|m| m >= val))
+    } else {
+        false
+    }
+}
+
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    let local_valid = !local.is_empty()
+        && local.len() <= 64
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local.chars().all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+/=?^_`{|}~.-".contains(c));
+    let labels = domain.split('.').collect::<Vec<&str>>();
+    let domain_valid = domain.len() <= 253
+        && labels.len() >= 2
+        && labels.iter().all(|l| {
+            !l.is_empty()
+                && l.len() <= 63
+                && !l.starts_with('-')
+                && !l.ends_with('-')
+                && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    local_valid && domain_valid
+}
+
+fn is_url(value: &str, schemes: &[String]) -> bool {
+    match Url::parse(value) {
+        Ok(url) => url.has_host() && if schemes.is_empty() {
+            url.scheme() == "http" || url.scheme() == "https"
+        } else {
+            schemes.iter().any(|s| s.eq_ignore_ascii_case(url.scheme()))
+        },
+        Err(_) => false
+    }
+}
+
+fn is_color(value: &str) -> bool {
+    value.strip_prefix('#').is_some_and(|hex| (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// A test against the values of sibling fields in the same field list. A sibling without a valid
+/// value is compared using its default.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FieldCondition {
+    Equals { key: String, value: Value },
+    NotEquals { key: String, value: Value },
+    OneOf { key: String, values: Vec<Value> },
+
+    /// The sibling has a value that isn't null, an empty string or an empty list
+    Present { key: String },
+    All { conditions: Vec<FieldCondition> },
+    Any { conditions: Vec<FieldCondition> },
+    Not { condition: Box<FieldCondition> }
+}
+
+impl FieldCondition {
+    pub fn equals<Key: AsRef<str>, T: Serialize>(key: Key, value: T) -> Self {
+        FieldCondition::Equals { key: key.as_ref().to_string(), value: serde_json::to_value(value).unwrap_or(Value::Null) }
+    }
+
+    pub fn not_equals<Key: AsRef<str>, T: Serialize>(key: Key, value: T) -> Self {
+        FieldCondition::NotEquals { key: key.as_ref().to_string(), value: serde_json::to_value(value).unwrap_or(Value::Null) }
+    }
+
+    pub fn present<Key: AsRef<str>>(key: Key) -> Self {
+        FieldCondition::Present { key: key.as_ref().to_string() }
+    }
+
+    pub fn evaluate(&self, values: &HashMap<String, Value>) -> bool {
+        let get = |key: &String| values.get(key).cloned().unwrap_or(Value::Null);
+        match self {
+            FieldCondition::Equals { key, value } => get(key) == *value,
+            FieldCondition::NotEquals { key, value } => get(key) != *value,
+            FieldCondition::OneOf { key, values } => values.contains(&get(key)),
+            FieldCondition::Present { key } => match get(key) {
+                Value::Null => false,
+                Value::String(v) => !v.is_empty(),
+                Value::Array(v) => !v.is_empty(),
+                _ => true
+            },
+            FieldCondition::All { conditions } => conditions.iter().all(|c| c.evaluate(values)),
+            FieldCondition::Any { conditions } => conditions.iter().any(|c| c.evaluate(values)),
+            FieldCondition::Not { condition } => !condition.evaluate(values)
+        }
+    }
+
+    /// Every sibling key the condition refers to.
+    pub fn keys(&self) -> Vec<String> {
+        match self {
+            FieldCondition::Equals { key, .. }
+            | FieldCondition::NotEquals { key, .. }
+            | FieldCondition::OneOf { key, .. }
+            | FieldCondition::Present { key } => vec![key.clone()],
+            FieldCondition::All { conditions } | FieldCondition::Any { conditions } => conditions.iter().flat_map(|c| c.keys()).collect(),
+            FieldCondition::Not { condition } => condition.keys()
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Builder)]
@@ -118,13 +358,32 @@ pub struct PluginArgument {
 
     #[serde(default)]
     #[builder(default = "None")]
-    pub default: Option<Value>
+    pub default: Option<Value>,
+
+    /// Only shows the field when this holds. Hidden fields are neither required nor validated,
+    /// and their values aren't passed to the plugin.
+    #[serde(default)]
+    #[builder(default = "None")]
+    pub visible_when: Option<FieldCondition>,
+
+    /// Requires the field when this holds, in addition to when `required` is set
+    #[serde(default)]
+    #[builder(default = "None")]
+    pub required_when: Option<FieldCondition>
 }
 
 impl PluginArgument {
+    pub fn is_visible(&self, values: &HashMap<String, Value>) -> bool {
+        self.visible_when.as_ref().is_none_or(|c| c.evaluate(values))
+    }
+
+    pub fn is_required(&self, values: &HashMap<String, Value>) -> bool {
+        self.required || self.required_when.as_ref().is_some_and(|c| c.evaluate(values))
+    }
+
     pub fn validate(&self, value: Value) -> bool {
         match self.field.clone() {
-            FieldType::Text {..} => value.as_str().map_or(false, |_| true),
+            FieldType::Text { validation, min_length, max_length, .. } => is_valid_text(&value, &validation, min_length, max_length),
             FieldType::Number {kind, min, max, ..} => {
                 match kind {
                     NumberType::Integer => {
@@ -179,7 +438,7 @@ impl PluginArgument {
                 }
             },
             FieldType::Switch {} => value.as_bool().is_some(),
-            FieldType::TextArea { .. } => value.as_str().is_some(),
+            FieldType::TextArea { min_length, max_length, .. } => is_valid_text(&value, &None, min_length, max_length),
             FieldType::PluginDefined { expected_type, .. } => match expected_type {
                 ExpectedType::Boolean => value.as_bool().is_some(),
                 ExpectedType::Float => value.as_f64().is_some(),
@@ -187,7 +446,13 @@ impl PluginArgument {
                 ExpectedType::Unsigned => value.as_u64().is_some(),
                 ExpectedType::String => value.as_str().is_some(),
                 ExpectedType::StringArray => value.as_array().is_some_and(|arr| arr.iter().all(|v| v.as_str().is_some()))
-            }
+            },
+            FieldType::Date { min, max } => within_bounds(&value, &min, &max, parse_date),
+            FieldType::DateTime { min, max } => within_bounds(&value, &min, &max, parse_datetime),
+            FieldType::Email { .. } => value.as_str().is_some_and(is_email),
+            FieldType::Url { schemes, .. } => value.as_str().is_some_and(|v| is_url(v, &schemes)),
+            FieldType::Color {} => value.as_str().is_some_and(is_color),
+            FieldType::File { .. } => value.as_str().is_some_and(|v| !v.is_empty())
         }
     }
 }
@@ -196,6 +461,7 @@ impl PluginArgument {
 pub struct ValidatedArgument {
     pub argument: PluginArgument,
     pub valid: bool,
+    pub visible: bool,
     pub value: Option<Value>,
     pub previous: Option<Value>
 }
@@ -206,20 +472,56 @@ pub struct ValidationResult {
     pub arguments: HashMap<String, ValidatedArgument>
 }
 
+impl ValidationResult {
+    pub fn values(&self) -> HashMap<String, Value> {
+        self.arguments
+            .iter()
+            .filter_map(|(key, arg)| arg.value.clone().map(|v| (key.clone(), v)))
+            .collect()
+    }
+
+    pub fn invalid_keys(&self) -> Vec<String> {
+        self.arguments
+            .iter()
+            .filter_map(|(key, arg)| if arg.valid { None } else { Some(key.clone()) })
+            .collect()
+    }
+}
+
 pub trait ArgValidator {
     fn validate(&self, fields: HashMap<String, Value>) -> ValidationResult;
 }
 
 impl ArgValidator for Vec<PluginArgument> {
     fn validate(&self, fields: HashMap<String, Value>) -> ValidationResult {
+        // Conditions see each sibling's value if it's valid, or its default otherwise
+        let effective: HashMap<String, Value> = self.iter().filter_map(|arg| {
+            match fields.get(&arg.key) {
+                Some(val) if arg.validate(val.clone()) => Some(val.clone()),
+                _ => arg.default.clone()
+            }.map(|v| (arg.key.clone(), v))
+        }).collect();
+
         let mut results: HashMap<String, ValidatedArgument> = HashMap::new();
         let mut is_valid = true;
         for arg in self {
+            if !arg.is_visible(&effective) {
+                results.insert(arg.key.clone(), ValidatedArgument {
+                    argument: arg.clone(),
+                    valid: true,
+                    visible: false,
+                    value: None,
+                    previous: fields.get(&arg.key).cloned()
+                });
+                continue;
+            }
+
             if let Some(val) = fields.get(&arg.key) {
                 if arg.validate(val.clone()) {
                     results.insert(arg.key.clone(), ValidatedArgument {
                         argument: arg.clone(),
                         valid: true,
+                        visible: true,
                         value: Some(val.clone()),
                         previous: None
                     });
@@ -227,19 +529,22 @@ impl ArgValidator for Vec<PluginArgument> {
                     results.insert(arg.key.clone(), ValidatedArgument {
                         argument: arg.clone(),
                         valid: false,
+                        visible: true,
                         value: arg.default.clone(),
                         previous: Some(val.clone())
                     });
                     is_valid = false;
                 }
             } else {
+                let required = arg.is_required(&effective);
                 results.insert(arg.key.clone(), ValidatedArgument {
                     argument: arg.clone(),
-                    valid: !arg.required,
+                    valid: !required,
+                    visible: true,
                     value: arg.default.clone(),
                     previous: None
                 });
-                if arg.required {
+                if required {
                     is_valid = false;
                 }
             }
@@ -253,6 +558,13 @@ impl ArgValidator for Vec<PluginArgument> {
     }
 }
 
+/// A struct whose fields double as a plugin's field list. Derive it with `#[derive(PluginFields)]`
+/// so the `PluginArgument`s a plugin declares and the values it reads back always line up.
+pub trait PluginFields: Sized {
+    fn fields() -> Vec<PluginArgument>;
+    fn from_params(params: &params::ParameterMap) -> Result<Self, Box<dyn std::error::Error>>;
+}
+
 impl FieldBuilder {
     pub fn minimal<Key: AsRef<str>, Label: AsRef<str>>(key: Key, label: Label, field: FieldType) -> Self {
         Self {
@@ -262,7 +574,9 @@ impl FieldBuilder {
             icon: None,
             required: Some(false),
             default: None,
-            description: None
+            description: None,
+            visible_when: None,
+            required_when: None
         }
     }
 }
@@ -325,7 +639,7 @@ impl AdminActionBuilder {
 
 impl AdminAction {
     pub fn get_argument(&self, key: impl AsRef<str>) -> Option<PluginArgument> {
-        self.arguments.iter().find(|f| f.key == key.as_ref().to_string()).cloned()
+        self.arguments.iter().find(|f| f.key == key.as_ref()).cloned()
     }
 }
 
@@ -363,15 +677,15 @@ pub struct GrantAction {
 
 impl GrantAction {
     pub fn get_option(&self, key: impl AsRef<str>) -> Option<PluginArgument> {
-        self.options.iter().find(|f| f.key == key.as_ref().to_string()).cloned()
+        self.options.iter().find(|f| f.key == key.as_ref()).cloned()
     }
 
     pub fn get_argument(&self, key: impl AsRef<str>) -> Option<PluginArgument> {
-        self.arguments.iter().find(|f| f.key == key.as_ref().to_string()).cloned()
+        self.arguments.iter().find(|f| f.key == key.as_ref()).cloned()
     }
 
     pub fn get_admin_action(&self, key: impl AsRef<str>) -> Option<AdminAction> {
-        self.admin_actions.iter().find(|f| f.key == key.as_ref().to_string()).cloned()
+        self.admin_actions.iter().find(|f| f.key == key.as_ref()).cloned()
     }
 }
 
@@ -540,14 +854,60 @@ impl GrantResource {
             Self::Generic { metadata, .. } => metadata,
             Self::Action { metadata, .. } => metadata,
         };
-        if let Some(data) = serialized {
-            if let Ok(meta) = serde_json::from_value::<Meta>(data.clone()) {
-                Some(meta)
-            } else {
-                None
-            }
-        } else {
-            None
+        serialized.as_ref().and_then(|data| serde_json::from_value::<Meta>(data.clone()).ok())
+    }
+}
+
+/// How often a scheduled task runs. Cron expressions use the seconds-first, six or seven field
+/// syntax (e.g. `0 0 3 * * *` for 3 AM daily) and are evaluated in UTC.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskSchedule {
+    Interval { seconds: u64 },
+    Cron { expression: String }
+}
+
+/// Which configuration profiles a scheduled task runs under.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskConfig {
+    /// Runs once, without a configuration profile
+    #[default]
+    None,
+
+    /// Runs once for every configuration profile of the plugin
+    All,
+
+    /// Runs under the configuration profile with this name, if it exists
+    Named { name: String }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Builder)]
+#[builder(setter(into, strip_option))]
+pub struct ScheduledTask {
+    pub key: String,
+    pub method: String,
+    pub label: String,
+    pub schedule: TaskSchedule,
+
+    #[serde(default)]
+    #[builder(default)]
+    pub config: TaskConfig,
+
+    #[serde(default)]
+    #[builder(default = "None")]
+    pub description: Option<String>
+}
+
+impl ScheduledTaskBuilder {
+    pub fn minimal<Key: AsRef<str>, Method: AsRef<str>, Label: AsRef<str>>(key: Key, method: Method, label: Label, schedule: TaskSchedule) -> Self {
+        Self {
+            key: Some(key.as_ref().to_string()),
+            method: Some(method.as_ref().to_string()),
+            label: Some(label.as_ref().to_string()),
+            schedule: Some(schedule),
+            config: None,
+            description: None
         }
     }
 }
@@ -581,6 +941,16 @@ pub struct PluginMetadata {
     #[serde(default)]
     #[builder(default)]
     pub config: Vec<PluginArgument>,
+
+    /// Hosts the plugin may make HTTP requests to. Entries may reference config fields
+    /// as `{key}` (e.g. `{host}`), which the server expands from each config profile.
+    #[serde(default)]
+    #[builder(default)]
+    pub allowed_hosts: Vec<String>,
+
+    #[serde(default)]
+    #[builder(default)]
+    pub tasks: Vec<ScheduledTask>,
 }
 
 impl PluginMetadataBuilder {
@@ -594,8 +964,23 @@ impl PluginMetadataBuilder {
             url: None,
             description: None,
             icon: None,
-            config: None
+            config: None,
+            allowed_hosts: None,
+            tasks: None
+        }
+    }
+
+    pub fn with_allowed_host<Host: AsRef<str>>(&mut self, host: Host) -> &mut Self {
+        if self.allowed_hosts.is_none() {
+            self.allowed_hosts(Vec::new());
+        }
+
+        let mut hosts = self.allowed_hosts.clone().unwrap();
+        if !hosts.contains(&host.as_ref().to_string()) {
+            hosts.push(host.as_ref().to_string());
         }
+        self.allowed_hosts(hosts);
+        self
     }
 
     pub fn with_grant(&mut self, grant: GrantAction) -> &mut Self {
@@ -609,6 +994,18 @@ impl PluginMetadataBuilder {
         self
     }
 
+    pub fn with_task(&mut self, task: ScheduledTask) -> &mut Self {
+        if self.tasks.is_none() {
+            self.tasks(Vec::new());
+        }
+
+        let mut tasks = self.tasks.clone().unwrap();
+        tasks.push(task);
+        tasks.dedup_by(|a, b| a.key.eq_ignore_ascii_case(&b.key));
+        self.tasks(tasks);
+        self
+    }
+
     pub fn with_config(&mut self, field: PluginArgument) -> &mut Self {
         if self.config.is_none() {
             self.config(Vec::new());
@@ -629,29 +1026,76 @@ impl PluginMetadataBuilder {
                 }
             }
         }
+
+        let config = self.config.clone().unwrap_or_default();
+        for host in self.allowed_hosts.clone().unwrap_or_default() {
+            for template in host.split('{').skip(1).filter_map(|part| part.split_once('}')).map(|(key, _)| key) {
+                if !config.iter().any(|field| field.key == template) {
+                    return Err(format!("Allowed host {host} references unknown config field {template}"));
+                }
+            }
+        }
         Ok(())
     }
 }
 
 
 
+/// Marks an argon2 hash of a legacy SHA-512 digest, made by [`HashedPassword::upgrade`].
+const UPGRADED_PREFIX: &str = "sha512+";
+
+/// A salted argon2 password hash in PHC format. Unsalted SHA-512 hex digests made by older plugins
+/// still verify, and can be rehashed in place with [`HashedPassword::upgrade`].
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct HashedPassword(String);
 
 impl HashedPassword {
     pub fn new<T: AsRef<str>>(password: T) -> Self {
-        Self(Self::hash(password.as_ref()))
+        Self::try_new(password).expect("Failed to hash password")
+    }
+
+    pub fn try_new<T: AsRef<str>>(password: T) -> Result<Self, argon2::password_hash::Error> {
+        Ok(Self(Self::hash(password.as_ref().as_bytes())?))
+    }
+
+    fn hash(input: &[u8]) -> Result<String, argon2::password_hash::Error> {
+        let salt = SaltString::generate(&mut OsRng);
+        Ok(Argon2::default().hash_password(input, &salt)?.to_string())
     }
 
-    fn hash(input: impl AsRef<str>) -> String {
+    fn legacy_hash(input: impl AsRef<str>) -> String {
         let mut hasher = Sha512::new();
         hasher.input_str(input.as_ref());
         hasher.result_str()
     }
 
+    fn verify_hash(hash: &str, input: &[u8]) -> bool {
+        PasswordHash::new(hash).is_ok_and(|h| Argon2::default().verify_password(input, &h).is_ok())
+    }
+
+    /// Whether this is a bare SHA-512 digest that should be upgraded.
+    pub fn is_legacy(&self) -> bool {
+        !self.0.starts_with('$') && !self.0.starts_with(UPGRADED_PREFIX)
+    }
+
     pub fn verify<T: AsRef<str>>(&self, test: T) -> bool {
-        let check = Self::hash(test.as_ref());
-        check == self.0
+        if let Some(hash) = self.0.strip_prefix(UPGRADED_PREFIX) {
+            Self::verify_hash(hash, Self::legacy_hash(test).as_bytes())
+        } else if self.is_legacy() {
+            Self::legacy_hash(test) == self.0
+        } else {
+            Self::verify_hash(&self.0, test.as_ref().as_bytes())
+        }
+    }
+
+    /// Rehashes a legacy SHA-512 digest with argon2, which doesn't need the original password.
+    /// Returns `None` if the hash is already salted.
+    pub fn upgrade(&self) -> Option<Self> {
+        if self.is_legacy() {
+            Self::hash(self.0.as_bytes()).ok().map(|h| Self(format!("{UPGRADED_PREFIX}{h}")))
+        } else {
+            None
+        }
     }
 }
 
@@ -662,6 +1106,13 @@ pub struct PluginFileData {
     pub content_type: String,
 }
 
+/// File details without the content, used alongside the raw byte `fs_*_raw` host functions.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PluginFileMetadata {
+    pub filename: Option<String>,
+    pub content_type: String,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ExtError(String);
 
@@ -686,11 +1137,11 @@ impl<T> From<Result<T, ExtError>> for ExtResult<T> {
     }
 }
 
-impl<T> Into<Result<T, ExtError>> for ExtResult<T> {
-    fn into(self) -> Result<T, ExtError> {
-        match self {
-            Self::Ok(v) => Ok(v),
-            Self::Err(e) => Err(e)
+impl<T> From<ExtResult<T>> for Result<T, ExtError> {
+    fn from(value: ExtResult<T>) -> Self {
+        match value {
+            ExtResult::Ok(v) => Ok(v),
+            ExtResult::Err(e) => Err(e)
         }
     }
 }
@@ -714,3 +1165,256 @@ impl<'a, T: FromBytes<'a> + Serialize + DeserializeOwned> FromBytes<'a> for ExtR
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_field(content_types: &[&str], max_size: Option<u64>) -> FieldType {
+        FieldType::File { content_types: content_types.iter().map(|t| t.to_string()).collect(), max_size }
+    }
+
+    #[test]
+    fn accepts_file_matches_exact_and_family_types() {
+        let field = file_field(&["application/pdf", "image/*"], None);
+        assert!(field.accepts_file("application/pdf", 10));
+        assert!(field.accepts_file("Image/PNG", 10));
+        assert!(field.accepts_file("application/pdf; charset=binary", 10));
+        assert!(!field.accepts_file("text/plain", 10));
+        assert!(!field.accepts_file("image", 10));
+    }
+
+    #[test]
+    fn accepts_file_with_no_types_accepts_anything() {
+        assert!(file_field(&[], None).accepts_file("application/octet-stream", u64::MAX));
+    }
+
+    #[test]
+    fn accepts_file_enforces_max_size() {
+        let field = file_field(&[], Some(1024));
+        assert!(field.accepts_file("text/plain", 1024));
+        assert!(!field.accepts_file("text/plain", 1025));
+    }
+
+    #[test]
+    fn accepts_file_rejects_other_field_types() {
+        assert!(!FieldType::Color {}.accepts_file("image/png", 0));
+    }
+
+    #[test]
+    fn text_validation_counts_characters_and_matches_pattern() {
+        let pattern = Some(String::from("^[a-z]+$"));
+        assert!(is_valid_text(&Value::from("éa"), &None, Some(2), Some(2)));
+        assert!(is_valid_text(&Value::from("abc"), &pattern, None, None));
+        assert!(is_valid_text(&Value::from("xyz"), &pattern, None, None));
+        assert!(!is_valid_text(&Value::from("ABC"), &pattern, None, None));
+        assert!(!is_valid_text(&Value::from(1), &None, None, None));
+    }
+
+    #[test]
+    fn invalid_text_pattern_rejects_every_value() {
+        let pattern = Some(String::from("(unclosed"));
+        assert!(!is_valid_text(&Value::from("(unclosed"), &pattern, None, None));
+        assert!(!is_valid_text(&Value::from(""), &pattern, None, None));
+    }
+
+    #[derive(PluginFields)]
+    struct SampleFields {
+        #[field(label = "Host", validation = "^https?://")]
+        host: String,
+        #[field(default = 8096u16, min = 1, max = 65535)]
+        port: u16,
+        api_key: Option<String>,
+        #[field(key = "use_tls", default = false)]
+        tls: bool,
+        #[field(visible_when = FieldCondition::equals("use_tls", true))]
+        certificate: Option<String>,
+    }
+
+    fn params(values: Value) -> params::ParameterMap {
+        serde_json::from_value::<HashMap<String, Value>>(values).unwrap().into()
+    }
+
+    #[test]
+    fn derived_fields_follow_the_struct() {
+        let fields = SampleFields::fields();
+        let keys: Vec<&str> = fields.iter().map(|f| f.key.as_str()).collect();
+        assert_eq!(keys, ["host", "port", "api_key", "use_tls", "certificate"]);
+
+        assert_eq!(fields[0].label, "Host");
+        assert!(fields[0].required);
+        assert!(matches!(&fields[0].field, FieldType::Text { validation: Some(v), .. } if v == "^https?://"));
+        assert!(matches!(fields[1].field, FieldType::Number { kind: NumberType::Unsigned, min: Some(1.0), max: Some(65535.0), .. }));
+        assert_eq!(fields[1].default, Some(Value::from(8096)));
+        assert!(!fields[2].required);
+        assert!(matches!(fields[3].field, FieldType::Switch {}));
+        assert_eq!(fields[3].default, Some(Value::from(false)));
+        assert!(!fields[4].required);
+        assert!(matches!(fields[4].visible_when, Some(FieldCondition::Equals { ref key, .. }) if key == "use_tls"));
+    }
+
+    #[test]
+    fn derived_from_params_reads_values_and_defaults() {
+        let sample = SampleFields::from_params(&params(serde_json::json!({
+            "host": "https://media.example.com",
+            "api_key": null,
+            "use_tls": true,
+            "certificate": "cert"
+        }))).unwrap();
+        assert_eq!(sample.host, "https://media.example.com");
+        assert_eq!(sample.port, 8096);
+        assert_eq!(sample.api_key, None);
+        assert!(sample.tls);
+        assert_eq!(sample.certificate.as_deref(), Some("cert"));
+
+        let sample = SampleFields::from_params(&params(serde_json::json!({
+            "host": "http://media.example.com",
+            "port": 80
+        }))).unwrap();
+        assert_eq!(sample.port, 80);
+        assert!(!sample.tls);
+        assert_eq!(sample.certificate, None);
+    }
+
+    #[test]
+    fn derived_from_params_requires_non_optional_fields() {
+        assert!(SampleFields::from_params(&params(serde_json::json!({ "port": 80 }))).is_err());
+    }
+
+    fn values(values: Value) -> HashMap<String, Value> {
+        serde_json::from_value(values).unwrap()
+    }
+
+    #[test]
+    fn conditions_compare_sibling_values() {
+        let values = values(serde_json::json!({ "mode": "ldap", "tags": [], "name": "" }));
+        assert!(FieldCondition::equals("mode", "ldap").evaluate(&values));
+        assert!(FieldCondition::not_equals("mode", "local").evaluate(&values));
+        assert!(FieldCondition::OneOf { key: String::from("mode"), values: vec![Value::from("local"), Value::from("ldap")] }.evaluate(&values));
+        assert!(!FieldCondition::equals("missing", "ldap").evaluate(&values));
+    }
+
+    #[test]
+    fn present_conditions_ignore_empty_values() {
+        let values = values(serde_json::json!({ "tags": [], "name": "", "count": 0, "flag": false }));
+        assert!(!FieldCondition::present("tags").evaluate(&values));
+        assert!(!FieldCondition::present("name").evaluate(&values));
+        assert!(!FieldCondition::present("missing").evaluate(&values));
+        assert!(FieldCondition::present("count").evaluate(&values));
+        assert!(FieldCondition::present("flag").evaluate(&values));
+    }
+
+    #[test]
+    fn conditions_combine() {
+        let values = values(serde_json::json!({ "a": 1, "b": 2 }));
+        let (a, b) = (FieldCondition::equals("a", 1), FieldCondition::equals("b", 3));
+        assert!(!FieldCondition::All { conditions: vec![a.clone(), b.clone()] }.evaluate(&values));
+        assert!(FieldCondition::Any { conditions: vec![a.clone(), b.clone()] }.evaluate(&values));
+        assert!(FieldCondition::Not { condition: Box::new(b) }.evaluate(&values));
+        assert!(FieldCondition::All { conditions: Vec::new() }.evaluate(&values));
+        assert!(!FieldCondition::Any { conditions: Vec::new() }.evaluate(&values));
+    }
+
+    fn conditional_fields() -> Vec<PluginArgument> {
+        vec![
+            PluginArgument {
+                key: String::from("tls"),
+                label: String::from("TLS"),
+                field: FieldType::Switch {},
+                description: None,
+                icon: None,
+                required: false,
+                default: Some(Value::from(false)),
+                visible_when: None,
+                required_when: None,
+            },
+            PluginArgument {
+                key: String::from("certificate"),
+                label: String::from("Certificate"),
+                field: FieldType::Text { placeholder: None, password: false, validation: None, min_length: Some(1), max_length: None },
+                description: None,
+                icon: None,
+                required: true,
+                default: None,
+                visible_when: Some(FieldCondition::equals("tls", true)),
+                required_when: None,
+            },
+            PluginArgument {
+                key: String::from("note"),
+                label: String::from("Note"),
+                field: FieldType::Text { placeholder: None, password: false, validation: None, min_length: None, max_length: None },
+                description: None,
+                icon: None,
+                required: false,
+                default: None,
+                visible_when: None,
+                required_when: Some(FieldCondition::present("certificate")),
+            },
+        ]
+    }
+
+    #[test]
+    fn hidden_fields_are_neither_required_nor_passed_on() {
+        let result = conditional_fields().validate(values(serde_json::json!({ "certificate": "" })));
+        assert!(result.valid);
+        let certificate = &result.arguments["certificate"];
+        assert!(!certificate.visible);
+        assert!(certificate.valid);
+        assert_eq!(certificate.previous, Some(Value::from("")));
+        assert!(!result.values().contains_key("certificate"));
+    }
+
+    #[test]
+    fn visible_fields_are_required_and_validated() {
+        let fields = conditional_fields();
+        let result = fields.validate(values(serde_json::json!({ "tls": true })));
+        assert!(!result.valid);
+        assert_eq!(result.invalid_keys(), ["certificate"]);
+
+        let result = fields.validate(values(serde_json::json!({ "tls": true, "certificate": "" })));
+        assert!(!result.valid);
+        assert_eq!(result.invalid_keys(), ["certificate"]);
+    }
+
+    #[test]
+    fn required_when_follows_valid_sibling_values() {
+        let fields = conditional_fields();
+        let result = fields.validate(values(serde_json::json!({ "tls": true, "certificate": "cert" })));
+        assert!(!result.valid);
+        assert_eq!(result.invalid_keys(), ["note"]);
+
+        // An invalid value isn't seen by conditions, so the switch falls back to its default
+        let result = fields.validate(values(serde_json::json!({ "tls": "yes" })));
+        assert_eq!(result.invalid_keys(), ["tls"]);
+        assert!(!result.arguments["certificate"].visible);
+    }
+
+    #[test]
+    fn new_passwords_are_salted_argon2() {
+        let first = HashedPassword::new("hunter2");
+        let second = HashedPassword::new("hunter2");
+        assert!(!first.is_legacy());
+        assert_ne!(first.0, second.0);
+        assert!(first.verify("hunter2"));
+        assert!(!first.verify("hunter3"));
+        assert!(first.upgrade().is_none());
+    }
+
+    #[test]
+    fn legacy_digests_still_verify() {
+        let legacy = HashedPassword(HashedPassword::legacy_hash("hunter2"));
+        assert!(legacy.is_legacy());
+        assert!(legacy.verify("hunter2"));
+        assert!(!legacy.verify("hunter3"));
+    }
+
+    #[test]
+    fn upgraded_legacy_digests_verify_the_original_password() {
+        let upgraded = HashedPassword(HashedPassword::legacy_hash("hunter2")).upgrade().unwrap();
+        assert!(upgraded.0.starts_with(UPGRADED_PREFIX));
+        assert!(!upgraded.is_legacy());
+        assert!(upgraded.verify("hunter2"));
+        assert!(!upgraded.verify("hunter3"));
+        assert!(upgraded.upgrade().is_none());
+    }
+}
@@ -3,7 +3,7 @@ use std::{collections::HashMap, error::Error};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::GrantAction;
+use crate::{AdminAction, GrantAction, GrantResource, ScheduledTask};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct ParameterMap(HashMap<String, Value>);
@@ -15,7 +15,7 @@ impl ParameterMap {
     }
 
     pub fn get<T: DeserializeOwned>(&self, key: impl AsRef<str>) -> Result<Option<T>, Box<dyn Error>> {
-        if let Some(target) = self.0.get(&key.as_ref().to_string()) {
+        if let Some(target) = self.0.get(key.as_ref()) {
             Ok(Some(serde_json::from_value(target.clone())?))
         } else {
             Ok(None)
@@ -47,3 +47,64 @@ pub struct GrantActionParams {
     pub user_arguments: ParameterMap
 }
 
+
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GrantRevokeParams {
+    pub action: GrantAction,
+    pub plugin_config: ParameterMap,
+    pub service_config: ParameterMap,
+    pub resources: Vec<GrantResource>
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AdminActionParams {
+    pub action: AdminAction,
+    pub grant: GrantAction,
+    pub resource: GrantResource,
+    pub plugin_config: ParameterMap,
+    pub service_config: ParameterMap,
+    pub arguments: ParameterMap
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResourceActionParams {
+    pub grant: GrantAction,
+    pub resource: GrantResource,
+    pub metadata: Option<Value>,
+    pub plugin_config: ParameterMap,
+    pub service_config: ParameterMap,
+    pub arguments: ParameterMap
+}
+
+/// Passed to `on_config_saved` before a configuration profile is created or updated.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConfigSavedParams {
+    pub config_id: String,
+    pub name: String,
+    pub created: bool,
+    pub plugin_config: ParameterMap
+}
+
+/// Passed to `test_config` once a configuration profile has passed field validation.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ConfigTestParams {
+    pub config_id: String,
+    pub name: String,
+    pub plugin_config: ParameterMap
+}
+
+/// Passed to `healthcheck`. The plugin is checked once without a configuration profile, then once per profile.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct HealthCheckParams {
+    pub config_id: Option<String>,
+    pub plugin_config: Option<ParameterMap>
+}
+
+/// Passed to a scheduled task's export each time it runs.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScheduledTaskParams {
+    pub task: ScheduledTask,
+    pub config_id: Option<String>,
+    pub plugin_config: Option<ParameterMap>
+}
@@ -0,0 +1,23 @@
+//! wasm32-unknown-unknown has no entropy source of its own, so `getrandom` (and everything built on it,
+//! like password salts) is routed to the server's `random_bytes` host function.
+
+#[cfg(target_arch = "wasm32")]
+mod host {
+    use extism_pdk::*;
+
+    #[host_fn]
+    extern "ExtismHost" {
+        pub fn random_bytes(length: u64) -> Vec<u8>;
+    }
+
+    fn fill(buf: &mut [u8]) -> Result<(), getrandom::Error> {
+        let bytes = unsafe { random_bytes(buf.len() as u64) }.or(Err(getrandom::Error::UNSUPPORTED))?;
+        if bytes.len() != buf.len() {
+            return Err(getrandom::Error::UNEXPECTED);
+        }
+        buf.copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    getrandom::register_custom_getrandom!(fill);
+}
@@ -0,0 +1,335 @@
+//! In-memory fakes of the server's host functions, for unit testing plugin code natively with
+//! `cargo test`. Enable the `testing` feature on the plugin's `invex-sdk` dev-dependency, script the
+//! upstream service with [`mock_http`], then call the plugin's functions directly.
+//!
+//! Fake state is kept per thread, so tests running in parallel don't see each other's files or routes.
+
+use std::{cell::RefCell, collections::HashMap};
+
+use extism_pdk::{Error, HttpRequest};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::{
+    http::HttpResponse,
+    kv::KvScope,
+    params::{GrantActionParams, PluginFieldParams},
+    GrantAction, PluginFileData,
+};
+
+struct MockRoute {
+    method: String,
+    url: String,
+    response: Result<HttpResponse, String>,
+}
+
+impl MockRoute {
+    fn matches(&self, method: &str, url: &str) -> bool {
+        self.method.eq_ignore_ascii_case(method)
+            && match self.url.strip_suffix('*') {
+                Some(prefix) => url.starts_with(prefix),
+                None => self.url == url,
+            }
+    }
+}
+
+#[derive(Default)]
+struct FakeHost {
+    files: HashMap<String, PluginFileData>,
+    next_file: u64,
+    routes: Vec<MockRoute>,
+    requests: Vec<RecordedRequest>,
+    kv: HashMap<(KvScope, String), Value>,
+}
+
+thread_local! {
+    static HOST: RefCell<FakeHost> = RefCell::new(FakeHost::default());
+}
+
+/// An HTTP request a plugin made while under test.
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl RecordedRequest {
+    pub fn json<T: DeserializeOwned>(&self) -> Option<T> {
+        self.body.as_ref().and_then(|b| serde_json::from_slice(b).ok())
+    }
+}
+
+/// Clears all fake files, KV entries, routes and recorded requests on this thread.
+pub fn reset() {
+    HOST.with(|h| *h.borrow_mut() = FakeHost::default());
+}
+
+/// Answers requests for `method` and `url` with `response`. A URL ending in `*` matches by prefix,
+/// and routes registered later take priority.
+pub fn mock_http(method: impl AsRef<str>, url: impl AsRef<str>, response: HttpResponse) {
+    HOST.with(|h| {
+        h.borrow_mut().routes.push(MockRoute {
+            method: method.as_ref().to_string(),
+            url: url.as_ref().to_string(),
+            response: Ok(response),
+        })
+    });
+}
+
+/// Fails requests for `method` and `url` as if the upstream couldn't be reached.
+pub fn mock_http_error(method: impl AsRef<str>, url: impl AsRef<str>, message: impl AsRef<str>) {
+    HOST.with(|h| {
+        h.borrow_mut().routes.push(MockRoute {
+            method: method.as_ref().to_string(),
+            url: url.as_ref().to_string(),
+            response: Err(message.as_ref().to_string()),
+        })
+    });
+}
+
+/// Every HTTP request made since the last [`reset`], in order.
+pub fn requests() -> Vec<RecordedRequest> {
+    HOST.with(|h| h.borrow().requests.clone())
+}
+
+/// Adds a file as though the server already stored it, returning its ID.
+pub fn insert_file(file: PluginFileData) -> String {
+    HOST.with(|h| {
+        let mut host = h.borrow_mut();
+        host.next_file += 1;
+        let id = format!("file-{}", host.next_file);
+        host.files.insert(id.clone(), file);
+        id
+    })
+}
+
+pub fn file(id: impl AsRef<str>) -> Option<PluginFileData> {
+    HOST.with(|h| h.borrow().files.get(id.as_ref()).cloned())
+}
+
+pub fn files() -> HashMap<String, PluginFileData> {
+    HOST.with(|h| h.borrow().files.clone())
+}
+
+pub(crate) fn handle_request(req: &HttpRequest, body: Option<Vec<u8>>) -> Result<HttpResponse, Error> {
+    let method = req.method.clone().unwrap_or(String::from("GET"));
+    HOST.with(|h| {
+        let mut host = h.borrow_mut();
+        host.requests.push(RecordedRequest {
+            method: method.clone(),
+            url: req.url.clone(),
+            headers: req.headers.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            body,
+        });
+        match host.routes.iter().rev().find(|r| r.matches(&method, &req.url)) {
+            Some(route) => route.response.clone().map_err(Error::msg),
+            None => Err(Error::msg(format!("No mocked response for {method} {}", req.url))),
+        }
+    })
+}
+
+/// Native definitions of the Extism kernel imports that `extism_pdk`'s logging macros reference, so
+/// plugin test binaries link. Logging is reported as disabled, so the memory functions are never reached.
+mod kernel {
+    #[no_mangle]
+    extern "C" fn get_log_level() -> i32 {
+        i32::MAX
+    }
+
+    #[no_mangle]
+    extern "C" fn alloc(_length: u64) -> u64 {
+        unreachable!("Extism memory is unavailable in native tests")
+    }
+
+    #[no_mangle]
+    extern "C" fn store_u8(_offs: u64, _data: u8) {
+        unreachable!("Extism memory is unavailable in native tests")
+    }
+
+    #[no_mangle]
+    extern "C" fn store_u64(_offs: u64, _data: u64) {
+        unreachable!("Extism memory is unavailable in native tests")
+    }
+
+    #[no_mangle]
+    extern "C" fn log_info(_offs: u64) {}
+
+    #[no_mangle]
+    extern "C" fn log_debug(_offs: u64) {}
+
+    #[no_mangle]
+    extern "C" fn log_warn(_offs: u64) {}
+
+    #[no_mangle]
+    extern "C" fn log_error(_offs: u64) {}
+
+    #[no_mangle]
+    extern "C" fn log_trace(_offs: u64) {}
+}
+
+/// Stand-ins for the `fs_*` host functions, with the same signatures `#[host_fn]` generates.
+#[allow(clippy::missing_safety_doc)]
+pub mod fs {
+    use extism_pdk::{Error, Json};
+
+    use crate::{PluginFileData, PluginFileMetadata};
+
+    use super::{file, insert_file};
+
+    pub unsafe fn fs_load(id: String) -> Result<Json<PluginFileData>, Error> {
+        file(&id).map(Json).ok_or(Error::msg(format!("Unknown file {id}")))
+    }
+
+    pub unsafe fn fs_store(data: Json<PluginFileData>) -> Result<String, Error> {
+        Ok(insert_file(data.into_inner()))
+    }
+
+    pub unsafe fn fs_info(id: String) -> Result<Json<PluginFileMetadata>, Error> {
+        let data = fs_load(id)?.into_inner();
+        Ok(Json(PluginFileMetadata { filename: data.filename, content_type: data.content_type }))
+    }
+
+    pub unsafe fn fs_load_raw(id: String) -> Result<Vec<u8>, Error> {
+        Ok(fs_load(id)?.into_inner().data)
+    }
+
+    pub unsafe fn fs_store_raw(metadata: Json<PluginFileMetadata>, data: Vec<u8>) -> Result<String, Error> {
+        let metadata = metadata.into_inner();
+        Ok(insert_file(PluginFileData { data, filename: metadata.filename, content_type: metadata.content_type }))
+    }
+}
+
+/// Stand-ins for the `kv_*` host functions. `Config` entries share one namespace, as though every
+/// call were made under the same configuration profile.
+#[allow(clippy::missing_safety_doc)]
+pub mod kv {
+    use extism_pdk::{Error, Json};
+    use serde_json::Value;
+
+    use crate::kv::{KvEntry, KvKey};
+
+    use super::HOST;
+
+    pub unsafe fn kv_get(key: Json<KvKey>) -> Result<Json<Option<Value>>, Error> {
+        let key = key.into_inner();
+        Ok(Json(HOST.with(|h| h.borrow().kv.get(&(key.scope, key.key)).cloned())))
+    }
+
+    pub unsafe fn kv_set(entry: Json<KvEntry>) -> Result<(), Error> {
+        let entry = entry.into_inner();
+        HOST.with(|h| h.borrow_mut().kv.insert((entry.scope, entry.key), entry.value));
+        Ok(())
+    }
+
+    pub unsafe fn kv_delete(key: Json<KvKey>) -> Result<Json<bool>, Error> {
+        let key = key.into_inner();
+        Ok(Json(HOST.with(|h| h.borrow_mut().kv.remove(&(key.scope, key.key)).is_some())))
+    }
+
+    pub unsafe fn kv_list(prefix: Json<KvKey>) -> Result<Json<Vec<KvEntry>>, Error> {
+        let prefix = prefix.into_inner();
+        Ok(Json(HOST.with(|h| {
+            h.borrow()
+                .kv
+                .iter()
+                .filter(|((scope, key), _)| *scope == prefix.scope && key.starts_with(&prefix.key))
+                .map(|((scope, key), value)| KvEntry { key: key.clone(), value: value.clone(), scope: *scope })
+                .collect()
+        })))
+    }
+}
+
+fn to_value(value: impl Serialize) -> Value {
+    serde_json::to_value(value).expect("Test parameters must serialize to JSON")
+}
+
+/// Builds `GrantActionParams` for calling a grant function directly.
+pub struct GrantParamsBuilder {
+    dry_run: bool,
+    action: GrantAction,
+    plugin_config: HashMap<String, Value>,
+    service_config: HashMap<String, Value>,
+    user_arguments: HashMap<String, Value>,
+}
+
+impl GrantParamsBuilder {
+    pub fn new(action: GrantAction) -> Self {
+        Self {
+            dry_run: false,
+            action,
+            plugin_config: HashMap::new(),
+            service_config: HashMap::new(),
+            user_arguments: HashMap::new(),
+        }
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn plugin_config(mut self, key: impl AsRef<str>, value: impl Serialize) -> Self {
+        self.plugin_config.insert(key.as_ref().to_string(), to_value(value));
+        self
+    }
+
+    pub fn service_config(mut self, key: impl AsRef<str>, value: impl Serialize) -> Self {
+        self.service_config.insert(key.as_ref().to_string(), to_value(value));
+        self
+    }
+
+    pub fn user_argument(mut self, key: impl AsRef<str>, value: impl Serialize) -> Self {
+        self.user_arguments.insert(key.as_ref().to_string(), to_value(value));
+        self
+    }
+
+    pub fn build(self) -> GrantActionParams {
+        GrantActionParams {
+            dry_run: self.dry_run,
+            action: self.action,
+            plugin_config: self.plugin_config.into(),
+            service_config: self.service_config.into(),
+            user_arguments: self.user_arguments.into(),
+        }
+    }
+}
+
+/// Builds the `PluginFieldParams` passed to plugin-defined field methods in each context.
+#[derive(Default)]
+pub struct FieldParamsBuilder {
+    plugin_config: HashMap<String, Value>,
+    service_config: HashMap<String, Value>,
+}
+
+impl FieldParamsBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn plugin_config(mut self, key: impl AsRef<str>, value: impl Serialize) -> Self {
+        self.plugin_config.insert(key.as_ref().to_string(), to_value(value));
+        self
+    }
+
+    pub fn service_config(mut self, key: impl AsRef<str>, value: impl Serialize) -> Self {
+        self.service_config.insert(key.as_ref().to_string(), to_value(value));
+        self
+    }
+
+    pub fn plugin(self) -> PluginFieldParams {
+        PluginFieldParams::PluginConfig {}
+    }
+
+    pub fn service(self) -> PluginFieldParams {
+        PluginFieldParams::ServiceConfig { plugin_config: self.plugin_config.into() }
+    }
+
+    pub fn invite(self) -> PluginFieldParams {
+        PluginFieldParams::InviteConfig {
+            plugin_config: self.plugin_config.into(),
+            service_config: self.service_config.into(),
+        }
+    }
+}
@@ -1,14 +1,15 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 
 use duration_string::DurationString;
 use mongodb::{options::ClientOptions, Client, Database};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum LogLevel {
     Trace,
     Debug,
+    #[default]
     Info,
     Warn,
     Error
@@ -26,16 +27,11 @@ impl Display for LogLevel {
     }
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {
-        Self::Info
-    }
-}
-
 #[derive(Deserialize, Clone, Debug)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum DatabaseConnection {
-    URL(String),
+    Url(String),
     Options(ClientOptions)
 }
 
@@ -48,14 +44,14 @@ pub struct DatabaseConfig {
 impl DatabaseConfig {
     pub async fn connect(&self) -> Result<Database, mongodb::error::Error> {
         let client = match &self.connection {
-            DatabaseConnection::URL(url) => Client::with_uri_str(url.clone()).await?,
+            DatabaseConnection::Url(url) => Client::with_uri_str(url.clone()).await?,
             DatabaseConnection::Options(opts) => Client::with_options(opts.clone())?
         };
         Ok(client.database(&self.database))
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct CustomizationConfig {
     #[serde(default)]
     pub server_name: Option<String>,
@@ -64,15 +60,6 @@ pub struct CustomizationConfig {
     pub server_welcome: Option<String>
 }
 
-impl Default for CustomizationConfig {
-    fn default() -> Self {
-        CustomizationConfig {
-            server_name: None,
-            server_welcome: None
-        }
-    }
-}
-
 #[derive(Deserialize, Clone, Debug)]
 pub struct AdminConfig {
     pub username: String,
@@ -82,6 +69,102 @@ pub struct AdminConfig {
     pub password: String
 }
 
+#[derive(Deserialize, Clone, Debug)]
+pub struct PluginPoolConfig {
+    #[serde(default = "PluginPoolConfig::default_max_instances")]
+    pub max_instances: usize,
+
+    #[serde(default = "PluginPoolConfig::default_max_idle")]
+    pub max_idle: usize,
+
+    #[serde(default)]
+    pub checkout_timeout: Option<DurationString>,
+
+    #[serde(default)]
+    pub plugins: HashMap<String, usize>
+}
+
+impl PluginPoolConfig {
+    fn default_max_instances() -> usize {
+        4
+    }
+
+    fn default_max_idle() -> usize {
+        2
+    }
+
+    pub fn max_instances_for(&self, plugin_id: impl AsRef<str>) -> usize {
+        self.plugins.get(plugin_id.as_ref()).cloned().unwrap_or(self.max_instances)
+    }
+}
+
+impl Default for PluginPoolConfig {
+    fn default() -> Self {
+        PluginPoolConfig {
+            max_instances: Self::default_max_instances(),
+            max_idle: Self::default_max_idle(),
+            checkout_timeout: None,
+            plugins: HashMap::new()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PluginLimits {
+    #[serde(default)]
+    pub timeout: Option<DurationString>,
+
+    #[serde(default)]
+    pub max_memory: Option<u64>,
+
+    #[serde(default)]
+    pub max_http_response: Option<u64>,
+
+    #[serde(default)]
+    pub fuel: Option<u64>
+}
+
+impl PluginLimits {
+    fn default_timeout() -> DurationString {
+        std::time::Duration::from_secs(30).into()
+    }
+
+    fn default_max_memory() -> u64 {
+        256 * 1024 * 1024
+    }
+
+    /// Applies a plugin's overrides on top of these limits. Plugins always get a timeout and a
+    /// memory limit, falling back to the built-in defaults if neither sets one.
+    pub fn merge(&self, overrides: Option<PluginLimits>) -> PluginLimits {
+        let overrides = overrides.unwrap_or_default();
+        PluginLimits {
+            timeout: overrides.timeout.or(self.timeout).or(Some(Self::default_timeout())),
+            max_memory: overrides.max_memory.or(self.max_memory).or(Some(Self::default_max_memory())),
+            max_http_response: overrides.max_http_response.or(self.max_http_response),
+            fuel: overrides.fuel.or(self.fuel)
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum UnsignedPolicy {
+    Reject,
+    #[default]
+    Warn,
+    Allow
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct PluginSigningConfig {
+    #[serde(default)]
+    pub unsigned: UnsignedPolicy,
+
+    /// Base64-encoded ed25519 public keys, by publisher name
+    #[serde(default)]
+    pub trusted_keys: HashMap<String, String>
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct Config {
     pub database: DatabaseConfig,
@@ -91,6 +174,41 @@ pub struct Config {
     #[serde(default)]
     pub plugin_logging: LogLevel,
 
+    /// Maximum size in bytes of the capped collection that stores plugin logs
+    #[serde(default = "Config::default_plugin_log_size")]
+    pub plugin_log_size: u64,
+
+    #[serde(default)]
+    pub plugin_pool: PluginPoolConfig,
+
+    #[serde(default)]
+    pub plugin_limits: PluginLimits,
+
+    #[serde(default)]
+    pub plugin_signing: PluginSigningConfig,
+
+    /// How often plugins installed from a URL are checked for updates
+    #[serde(default = "Config::default_plugin_update_interval")]
+    pub plugin_update_interval: DurationString,
+
+    /// How often enabled plugins and their configuration profiles are health-checked
+    #[serde(default = "Config::default_plugin_health_interval")]
+    pub plugin_health_interval: DurationString,
+
     #[serde(default)]
     pub customization: CustomizationConfig
+}
+
+impl Config {
+    fn default_plugin_log_size() -> u64 {
+        16 * 1024 * 1024
+    }
+
+    fn default_plugin_update_interval() -> DurationString {
+        std::time::Duration::from_secs(24 * 60 * 60).into()
+    }
+
+    fn default_plugin_health_interval() -> DurationString {
+        std::time::Duration::from_secs(5 * 60).into()
+    }
 }
\ No newline at end of file
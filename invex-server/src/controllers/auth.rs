@@ -15,7 +15,7 @@ struct LoginData {
 async fn login(session_obj: AuthSession, login: Json<LoginData>, users: Docs<AuthUser>, sessions: Docs<AuthSession>) -> Result<Json<ClientUser>, ApiError> {
     let mut session = session_obj.clone();
     if let Some(ref current_id) = session.user_id {
-        if let Some(_) = users.get(current_id.to_string()).await {
+        if users.get(current_id.to_string()).await.is_some() {
             return Err(ApiError::MethodNotAllowed("Already logged in".to_string()));
         }
     }
@@ -24,12 +24,12 @@ async fn login(session_obj: AuthSession, login: Json<LoginData>, users: Docs<Aut
         if user.verify(login.password.clone()) {
             session.user_id = Some(user.clone().id);
             let _ = sessions.save(session.clone()).await;
-            return Ok(Json(user.clone().into()));
+            Ok(Json(user.clone().into()))
         } else {
-            return Err(ApiError::NotFound("Unknown username or password".to_string()));
+            Err(ApiError::NotFound("Unknown username or password".to_string()))
         }   
     } else {
-        return Err(ApiError::NotFound("Unknown username or password".to_string()));
+        Err(ApiError::NotFound("Unknown username or password".to_string()))
     }
 }
 
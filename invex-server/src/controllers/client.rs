@@ -2,12 +2,11 @@ use std::collections::HashMap;
 
 use bson::doc;
 use chrono::Utc;
-use invex_sdk::{ params::{GrantActionParams, ParameterMap}, GrantResource, PluginArgument };
+use invex_sdk::{ call::ActionResult, params::{GrantActionParams, ParameterMap}, FieldType, GrantResource, PluginArgument };
 use rocket::{
-    request::{ self, FromRequest },
-    response::Responder,
+    form::Form,
+    fs::TempFile,
     serde::json::Json,
-    Request,
     Route,
 };
 use serde::{ Deserialize, Serialize };
@@ -15,72 +14,11 @@ use serde_json::Value;
 
 use crate::{
     models::{
-        auth::{ AuthSession, AuthUser, ClientUser }, client::ClientResource, error::ApiError, invite::{ GrantResult, Invite, InviteGrant, InviteUsage, ResolvedExpiration }, plugin::{ PluginConfiguration, PluginRegistry, RegisteredPlugin }, service::{ Service, ServiceGrant }
+        auth::{ AuthSession, AuthUser, ClientUser }, client::ClientResource, error::ApiError, invite::{ ArgumentUpload, GrantResult, GrantRevocation, Invite, InviteGrant, InviteUsage }, plugin::{ CallScope, HealthCheck, PluginConfiguration, PluginRegistry, RegisteredPlugin }, service::{ Service, ServiceGrant }
     },
-    util::{ database::{ Collections, Docs, Document, Id }, ApiResult },
+    util::{ database::{ Collections, Docs, Document, FileInfo, Fs, Id }, ApiResult },
 };
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
-pub struct UsingInvite {
-    pub usage: InviteUsage,
-    pub user: AuthUser,
-    pub invite: Invite,
-}
-
-impl<'r> UsingInvite {
-    async fn parse(req: &'r Request<'_>) -> Result<Self, ApiError> {
-        let code = req
-            .headers()
-            .get_one("X-InvexCode")
-            .ok_or(
-                ApiError::BadRequest("Request must include the X-InvexCode header".to_string())
-            )?;
-        let usages = req
-            .guard::<Docs<InviteUsage>>().await
-            .success_or(ApiError::Internal("Failed to fetch usages".to_string()))?;
-        let user = req
-            .guard::<AuthUser>().await
-            .success_or(ApiError::AuthenticationRequired("Must be authenticated".to_string()))?;
-        let invites = req
-            .guard::<Docs<Invite>>().await
-            .success_or(ApiError::Internal("Failed to fetch invites".to_string()))?;
-
-        let usage = usages
-            .query_one(doc! { "invite_code": code, "user": user.id.to_string() }).await
-            .ok_or(ApiError::NotFound("Specified invite not used by you.".to_string()))?;
-        let invite = invites
-            .get(usage.invite_id.to_string()).await
-            .ok_or(ApiError::NotFound("Associated invite does not exist".to_string()))?;
-
-        Ok(Self {
-            usage,
-            user,
-            invite,
-        })
-    }
-}
-
-#[rocket::async_trait]
-impl<'r> FromRequest<'r> for UsingInvite {
-    type Error = ApiError;
-
-    async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
-        match Self::parse(&req).await {
-            Ok(s) => request::Outcome::Success(s),
-            Err(e) => {
-                let resp = e.clone().respond_to(req);
-                request::Outcome::Error((
-                    match resp {
-                        Ok(r) => r.status(),
-                        Err(s) => s,
-                    },
-                    e,
-                ))
-            }
-        }
-    }
-}
-
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct RedeemingGrant {
     pub plugin: RegisteredPlugin,
@@ -92,11 +30,15 @@ pub struct RedeemingGrant {
     pub revocable: bool,
     pub url: Option<String>,
     pub help: Option<String>,
+
+    /// Latest health check of the configuration profile this grant runs under, if the plugin has one
+    pub health: Option<HealthCheck>,
 }
 
 impl RedeemingGrant {
     pub async fn from_ids(
         plugin_id: impl AsRef<str>,
+        config_id: impl AsRef<str>,
         grant_id: impl AsRef<str>,
         url: Option<String>,
         help: Option<String>,
@@ -114,6 +56,7 @@ impl RedeemingGrant {
                     revocable: grant.revoke_method.is_some(),
                     url,
                     help,
+                    health: plugin.health().and_then(|h| h.config(config_id)),
                 })
             } else {
                 Err(ApiError::NotFound("Unknown grant ID".to_string()))
@@ -145,11 +88,12 @@ impl RedeemingService {
             let mut additional_grants: i64 = 0;
             let mut actions: HashMap<String, RedeemingGrant> = HashMap::new();
             for (key, grant) in &service.grants {
-                if let ServiceGrant::Grant { plugin_id, grant_id, url, help, .. } = grant {
+                if let ServiceGrant::Grant { plugin_id, config_id, grant_id, url, help, .. } = grant {
                     actions.insert(
                         key.clone(),
                         RedeemingGrant::from_ids(
                             plugin_id.to_string(),
+                            config_id.to_string(),
                             grant_id.to_string(),
                             url.clone(),
                             help.clone(),
@@ -209,12 +153,7 @@ async fn get_invite_info(
 ) -> ApiResult<RedeemingInvite> {
     if let Some(invite) = invites.query_one(doc! { "code": code }).await {
         if let Ok(inv_usages) = usages.query_many(doc! { "invite_id": invite.id() }).await {
-            let expired = match invite.expires() {
-                ResolvedExpiration::Never => false,
-                ResolvedExpiration::Datetime(datetime) => Utc::now() <= datetime,
-                ResolvedExpiration::Uses(max_uses) => (inv_usages.len() as u64) < max_uses,
-            };
-            if expired {
+            if invite.is_expired(inv_usages.len() as u64) {
                 Err(ApiError::not_found("Unknown invite code"))
             } else {
                 Ok(
@@ -231,6 +170,83 @@ async fn get_invite_info(
     }
 }
 
+/// Uploads a file for a `File` argument of one of an invite's grants. The returned file ID is what
+/// the redemption should send as the argument's value. The file is only stored once its type and
+/// size have been checked against the argument.
+#[post("/redemption/<code>/files/<service_id>/<grant_id>/<field>", data = "<file>")]
+#[allow(clippy::too_many_arguments)]
+async fn upload_argument_file(
+    invites: Docs<Invite>,
+    usages: Docs<InviteUsage>,
+    services: Docs<Service>,
+    uploads: Docs<ArgumentUpload>,
+    plugins: PluginRegistry,
+    fs: Fs,
+    code: &str,
+    service_id: &str,
+    grant_id: &str,
+    field: &str,
+    file: Form<TempFile<'_>>
+) -> ApiResult<FileInfo> {
+    let invite = invites
+        .query_one(doc! { "code": code }).await
+        .ok_or(ApiError::not_found("Unknown invite code"))?;
+    let inv_usages = usages
+        .query_many(doc! { "invite_id": invite.id() }).await
+        .or(Err(ApiError::internal("Failed to retrieve invite usages")))?;
+    if invite.is_expired(inv_usages.len() as u64) {
+        return Err(ApiError::not_found("Unknown invite code"));
+    }
+    if !invite.services.iter().any(|s| s.to_string() == service_id) {
+        return Err(ApiError::not_found(format!("Missing service ID {service_id}")));
+    }
+
+    let service = services
+        .get(service_id.to_string()).await
+        .ok_or(ApiError::not_found("Unable to locate service ID."))?;
+    let Some(ServiceGrant::Grant { plugin_id, grant_id: grant_key, .. }) = service.grants.get(grant_id) else {
+        return Err(ApiError::not_found(format!("Missing grant ID {grant_id}")));
+    };
+    let action = plugins
+        .get(plugin_id.to_string()).await
+        .ok_or(ApiError::not_found("Unknown plugin ID"))?
+        .get_grant(grant_key.clone())
+        .ok_or(ApiError::not_found("Unknown grant key"))?;
+    let argument = action.arguments
+        .iter()
+        .find(|a| a.key == field && matches!(a.field, FieldType::File { .. }))
+        .ok_or(ApiError::not_found(format!("Unknown file argument {field}")))?;
+
+    let content_type = file
+        .content_type()
+        .map(|c| c.to_string())
+        .unwrap_or(String::from("application/octet-stream"));
+    if !argument.field.accepts_file(&content_type, file.len()) {
+        return Err(ApiError::bad_request(format!("File type or size not accepted for {field}")));
+    }
+
+    let mut data = file.open().await.or(Err(ApiError::bad_request("File was unreadable.")))?;
+    let stored = fs
+        .upload(&mut data, content_type, file.name().map(String::from)).await
+        .or(Err(ApiError::internal("Failed to store file")))?;
+    let recorded = uploads
+        .save(ArgumentUpload {
+            id: stored.id.clone(),
+            invite_code: code.to_string(),
+            service: service.id.clone(),
+            grant: grant_id.to_string(),
+            field: field.to_string(),
+            uploaded_at: Utc::now(),
+        }).await;
+
+    if recorded.is_ok() {
+        Ok(Json(stored.into()))
+    } else {
+        let _ = fs.delete(stored.id).await;
+        Err(ApiError::internal("Failed to record file upload"))
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "mode", rename_all = "snake_case")]
 pub enum InviteAuthenticator {
@@ -276,6 +292,7 @@ pub struct InviteRedemptionResponse {
 }
 
 #[post("/redemption/<code>/redeem?<dry>", data = "<data>")]
+#[allow(clippy::too_many_arguments)]
 async fn redeem_invite(
     invites: Docs<Invite>,
     usages: Docs<InviteUsage>,
@@ -283,6 +300,7 @@ async fn redeem_invite(
     sessions: Docs<AuthSession>,
     services: Docs<Service>,
     configs: Docs<PluginConfiguration>,
+    uploads: Docs<ArgumentUpload>,
     mut session: AuthSession,
     code: &str,
     data: Json<InviteRedemptionModel>,
@@ -292,12 +310,7 @@ async fn redeem_invite(
 ) -> ApiResult<InviteRedemptionResponse> {
     let redeem = (if let Some(invite) = invites.query_one(doc! { "code": code }).await {
         if let Ok(inv_usages) = usages.query_many(doc! { "invite_id": invite.id() }).await {
-            let expired = match invite.expires() {
-                ResolvedExpiration::Never => false,
-                ResolvedExpiration::Datetime(datetime) => Utc::now() <= datetime,
-                ResolvedExpiration::Uses(max_uses) => (inv_usages.len() as u64) < max_uses,
-            };
-            if expired {
+            if invite.is_expired(inv_usages.len() as u64) {
                 Err(ApiError::not_found("Unknown invite code"))
             } else {
                 Ok(RedeemingInvite::from_invite(&invite, collections.clone(), &plugins).await?)
@@ -325,17 +338,13 @@ async fn redeem_invite(
                 );
             }
 
-            let new_user = AuthUser::new_user(username, email, password).or_else(|e|
-                Err(ApiError::internal(format!("Failed to create user: {e:?}")))
-            )?;
+            let new_user = AuthUser::new_user(username, email, password).map_err(|e| ApiError::internal(format!("Failed to create user: {e:?}")))?;
             if !dry {
                 users
-                    .save(new_user.clone()).await
-                    .or_else(|e| Err(ApiError::internal(format!("Failed to save new user: {e:?}"))))?;
+                    .save(new_user.clone()).await.map_err(|e| ApiError::internal(format!("Failed to save new user: {e:?}")))?;
                 session.user_id = Some(new_user.id.clone());
                 sessions
-                    .save(session.clone()).await
-                    .or_else(|e| Err(ApiError::internal(format!("Failed to save session info: {e:?}"))))?;
+                    .save(session.clone()).await.map_err(|e| ApiError::internal(format!("Failed to save session info: {e:?}")))?;
             }
            
             Ok(new_user)
@@ -356,10 +365,7 @@ async fn redeem_invite(
                     if !dry {
                         session.user_id = Some(user.id.clone());
                         sessions
-                            .save(session.clone()).await
-                            .or_else(|e|
-                                Err(ApiError::internal(format!("Failed to save session info: {e:?}")))
-                            )?;
+                            .save(session.clone()).await.map_err(|e| ApiError::internal(format!("Failed to save session info: {e:?}")))?;
                     }
                     
                     Ok(user)
@@ -401,6 +407,7 @@ async fn redeem_invite(
     for service_reference in redeem.services {
         if let Some(service) = services.get(service_reference.id.clone()).await {
             let mut grants = HashMap::<String, GrantResult<Vec<GrantResource>>>::new();
+            let mut calls = HashMap::<String, Id>::new();
 
             for (grant_id, grant) in service.grants.clone() {
                 if
@@ -409,52 +416,69 @@ async fn redeem_invite(
                 {
                     grants.insert(
                         grant_id.clone(),
-                        (
-                            if let Some(plugin) = plugins.get(plugin_id.to_string()).await {
-                                if let Some(config) = configs.get(config_id.to_string()).await {
-                                    if let Some(action) = plugin.get_grant(grant_key.clone()) {
-                                        match data.get_parameters(service.id(), grant_id.clone()) {
-                                            Ok(user_params) => {
-                                                let params = GrantActionParams {
-                                                    dry_run: dry,
-                                                    action: action.clone(),
-                                                    plugin_config: config.options.into(),
-                                                    service_config: options.into(),
-                                                    user_arguments: user_params.clone()
-                                                };
-                                                let result = plugin.call::<_, Vec<GrantResource>>(action.method, params).await;
-                                                match result {
-                                                    Ok(resources) => Ok(resources),
-                                                    Err((error, code)) => Err(ApiError::bad_request(format!("Action execution failed with code {code}: {error:?}")))
-                                                }
-                                            },
-                                            Err(e) => Err(e)
-                                        }
-                                    } else {
-                                        Err(ApiError::not_found("Unknown grant key"))
+                        if let Some(plugin) = plugins.get(plugin_id.to_string()).await {
+                            if let Some(config) = configs.get(config_id.to_string()).await {
+                                if let Some(action) = plugin.get_grant(grant_key.clone()) {
+                                    let user_params = match data.get_parameters(service.id(), grant_id.clone()) {
+                                        Ok(user_params) => {
+                                            let checked = ArgumentUpload::check_arguments(
+                                                &uploads,
+                                                code,
+                                                service.id(),
+                                                grant_id.clone(),
+                                                &action.arguments,
+                                                &user_params,
+                                                !dry
+                                            ).await;
+                                            checked.and(Ok(user_params))
+                                        },
+                                        Err(e) => Err(e)
+                                    };
+                                    match user_params {
+                                        Ok(user_params) => {
+                                            let params = GrantActionParams {
+                                                dry_run: dry,
+                                                action: action.clone(),
+                                                plugin_config: config.options.into(),
+                                                service_config: options.into(),
+                                                user_arguments: user_params.clone()
+                                            };
+                                            let scope = CallScope::config(config.id.clone());
+                                            calls.insert(grant_id.clone(), scope.id.clone());
+                                            let result = plugin.call_scoped::<_, Vec<GrantResource>>(action.method, params, scope).await;
+                                            match result {
+                                                Ok(resources) => GrantResult::Success { value: resources },
+                                                Err(e) => e.into()
+                                            }
+                                        },
+                                        Err(e) => e.into()
                                     }
                                 } else {
-                                    Err(ApiError::not_found("Unknown config ID"))
+                                    ApiError::not_found("Unknown grant key").into()
                                 }
                             } else {
-                                Err(ApiError::not_found("Unknown plugin ID"))
+                                ApiError::not_found("Unknown config ID").into()
                             }
-                        ).into()
+                        } else {
+                            ApiError::not_found("Unknown plugin ID").into()
+                        }
                     );
                 }
             }
 
-            usage.grants.push(InviteGrant {service: service_reference.id.clone().into(), resources: Ok(grants).into()})
+            usage.grants.push(InviteGrant {service: service_reference.id.clone().into(), resources: Ok(grants).into(), revoked: HashMap::new(), calls})
         } else {
             usage.grants.push(InviteGrant {
                 service: service_reference.id.clone().into(),
                 resources: Err(ApiError::not_found("Unable to locate service ID.")).into(),
+                revoked: HashMap::new(),
+                calls: HashMap::new(),
             });
         }
     }
 
     if !dry {
-        usages.save(usage.clone()).await.or_else(|_| Err(ApiError::internal("Failed to save invite usage")))?;
+        usages.save(usage.clone()).await.map_err(|_| ApiError::internal("Failed to save invite usage"))?;
     }
 
     Ok(Json(InviteRedemptionResponse {
@@ -487,6 +511,25 @@ async fn get_resource_by_id(usages: Docs<InviteUsage>, user: AuthUser, plugins:
     }
 }
 
+#[post("/resources/<id>/<service_id>/<grant_id>/revoke")]
+async fn revoke_resource(usages: Docs<InviteUsage>, user: AuthUser, plugins: PluginRegistry, collections: Collections, id: &str, service_id: &str, grant_id: &str) -> ApiResult<GrantRevocation> {
+    if let Some(mut usage) = usages.query_one(doc! {"_id": id.to_string(), "user": user.id()}).await {
+        Ok(Json(usage.revoke(service_id, grant_id, user.id.clone(), &collections, &plugins).await?))
+    } else {
+        Err(ApiError::not_found("Invite usage not found"))
+    }
+}
+
+#[post("/resources/<id>/<service_id>/<grant_id>/<resource_id>/run", data = "<arguments>")]
+#[allow(clippy::too_many_arguments)]
+async fn run_resource_action(usages: Docs<InviteUsage>, user: AuthUser, plugins: PluginRegistry, collections: Collections, id: &str, service_id: &str, grant_id: &str, resource_id: &str, arguments: Json<HashMap<String, Value>>) -> ApiResult<ActionResult> {
+    if let Some(mut usage) = usages.query_one(doc! {"_id": id.to_string(), "user": user.id()}).await {
+        Ok(Json(usage.run_resource_action(service_id, grant_id, resource_id, arguments.into_inner(), &collections, &plugins).await?))
+    } else {
+        Err(ApiError::not_found("Invite usage not found"))
+    }
+}
+
 pub fn routes() -> Vec<Route> {
-    routes![get_invite_info, redeem_invite, get_resources, get_resource_by_id]
+    routes![get_invite_info, upload_argument_file, redeem_invite, get_resources, get_resource_by_id, revoke_resource, run_resource_action]
 }
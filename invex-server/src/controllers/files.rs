@@ -26,16 +26,13 @@ async fn get_file(user: AuthUser, id: &str, fs: Fs, usages: Docs<InviteUsage>, p
                     found = match resource.grant {
                         ClientResourceGrant::Attachment { file_id, .. } => file_id == id,
                         ClientResourceGrant::InlineImage { file_id, .. } => file_id == id,
-                        ClientResourceGrant::Plugin { result, .. } => match result {
-                            ClientResourcePluginGrant::Success { resources, .. } => resources.iter().any(|r| {
-                                if let GrantResource::File { file_id, .. } = r {
-                                    file_id == id
-                                } else {
-                                    false
-                                }
-                            }),
-                            _ => false
-                        },
+                        ClientResourceGrant::Plugin { result: ClientResourcePluginGrant::Success { resources, .. }, .. } => resources.iter().any(|r| {
+                            if let GrantResource::File { file_id, .. } = r {
+                                file_id == id
+                            } else {
+                                false
+                            }
+                        }),
                         _ => false
                     };
 
@@ -76,16 +73,13 @@ async fn get_file_metadata(user: AuthUser, id: &str, fs: Fs, usages: Docs<Invite
                     found = match resource.grant {
                         ClientResourceGrant::Attachment { file_id, .. } => file_id == id,
                         ClientResourceGrant::InlineImage { file_id, .. } => file_id == id,
-                        ClientResourceGrant::Plugin { result, .. } => match result {
-                            ClientResourcePluginGrant::Success { resources, .. } => resources.iter().any(|r| {
-                                if let GrantResource::File { file_id, .. } = r {
-                                    file_id == id
-                                } else {
-                                    false
-                                }
-                            }),
-                            _ => false
-                        },
+                        ClientResourceGrant::Plugin { result: ClientResourcePluginGrant::Success { resources, .. }, .. } => resources.iter().any(|r| {
+                            if let GrantResource::File { file_id, .. } = r {
+                                file_id == id
+                            } else {
+                                false
+                            }
+                        }),
                         _ => false
                     };
 
@@ -120,7 +114,7 @@ async fn delete_file(user: AuthUser, id: &str, fs: Fs) -> Result<(), ApiError> {
             "Must be an admin to delete files".to_string(),
         ));
     }
-    if let Some(_) = fs.get_file(id.to_string().into()).await {
+    if fs.get_file(id.to_string().into()).await.is_some() {
         fs.delete(id.to_string().into()).await.or(Err(ApiError::Internal("Failed to delete file".to_string())))
     } else {
         Err(ApiError::NotFound("File not found or inaccessible".to_string()))
@@ -194,7 +194,7 @@ async fn create_invite(
             alias: model.alias.clone()
         };
 
-        if let Ok(_) = invites.save(invite.clone()).await {
+        if invites.save(invite.clone()).await.is_ok() {
             Ok(
                 Json(InviteInfo {
                     id: invite.id.to_string(),
@@ -223,9 +223,9 @@ async fn delete_invite(
         return Err(ApiError::Forbidden("Must be an admin to delete/revoke invites".to_string()));
     }
 
-    if let Some(_) = invites.get(id).await {
+    if invites.get(id).await.is_some() {
         usages.delete_many(doc! {"invite_id": id}).await.map_err(|_| ApiError::Internal("Failed to delete existing usages".to_string()))?;
-        if let Ok(_) = invites.delete_one(doc! {"_id": id}).await {
+        if invites.delete_one(doc! {"_id": id}).await.is_ok() {
             Ok(Json(()))
         } else {
             Err(ApiError::Internal("Failed to delete result record".to_string()))
@@ -236,5 +236,5 @@ async fn delete_invite(
 }
 
 pub fn routes() -> Vec<Route> {
-    return routes![list_invites, get_invite, create_invite, delete_invite];
+    routes![list_invites, get_invite, create_invite, delete_invite]
 }
@@ -8,6 +8,7 @@ mod services;
 mod files;
 mod invite;
 mod client;
+mod usages;
 
 pub fn apply_routes(rocket: Rocket<Build>) -> Rocket<Build> {
     rocket
@@ -19,4 +20,5 @@ pub fn apply_routes(rocket: Rocket<Build>) -> Rocket<Build> {
         .mount("/files/", files::routes())
         .mount("/invites/", invite::routes())
         .mount("/client/", client::routes())
+        .mount("/usages/", usages::routes())
 }
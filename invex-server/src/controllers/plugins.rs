@@ -2,8 +2,15 @@ use std::collections::HashMap;
 
 use bson::doc;
 use invex_sdk::{
-    call::{ MethodCall, MethodReply, MethodResult },
-    params::PluginFieldParams,
+    call::{
+        ConfigTestResult,
+        LifecycleHook,
+        MethodCall,
+        MethodReply,
+        MethodResult,
+        TEST_CONFIG_EXPORT,
+    },
+    params::{ ConfigSavedParams, ConfigTestParams, PluginFieldParams },
     ArgValidator,
     FieldType,
     PluginDefinedMethodContext,
@@ -15,18 +22,60 @@ use serde::{ Deserialize, Serialize };
 use serde_json::Value;
 
 use crate::{
+    config::PluginLimits,
     models::{
         auth::{ AuthUser, UserType },
         error::ApiError,
-        plugin::{ PluginConfiguration, PluginInfo, PluginRegistry },
-        service::Service,
+        invite::{ Invite, InviteUsage },
+        plugin::{
+            CallScope,
+            Plugin,
+            PluginConfiguration,
+            PluginHealth,
+            PluginInfo,
+            PluginLog,
+            PluginRegistry,
+            PluginTaskRun,
+            PluginUpdate,
+        },
+        service::{ Service, ServiceGrant },
     },
-    util::{ database::{ Docs, File, Id }, ApiResult },
+    util::{ database::{ Docs, File, Id }, guards::InviteCode, signing::SignatureStatus, validation::ValidationReport, ApiResult },
 };
 
-#[post("/add/file", data = "<plugin>")]
+/// Plugin metadata along with any structural problems found in it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PluginPreview {
+    #[serde(flatten)]
+    pub metadata: PluginMetadata,
+    pub validation: ValidationReport,
+    pub signature: SignatureStatus,
+}
+
+impl From<PluginInfo> for PluginPreview {
+    fn from(value: PluginInfo) -> Self {
+        PluginPreview {
+            metadata: value.metadata,
+            validation: value.validation,
+            signature: value.signature,
+        }
+    }
+}
+
+#[derive(FromForm)]
+struct PluginUpload<'r> {
+    pub plugin: File,
+
+    /// Detached ed25519 signature of the plugin, as raw bytes or base64
+    pub signature: Option<&'r [u8]>,
+
+    /// Hosts to approve, or none to keep the approvals of an already installed plugin
+    pub approved_hosts: Option<Vec<String>>,
+}
+
+#[post("/add/file", data = "<upload>")]
 async fn add_plugin_file(
-    plugin: Form<File>,
+    upload: Form<PluginUpload<'_>>,
     user: AuthUser,
     plugins: PluginRegistry
 ) -> ApiResult<PluginInfo> {
@@ -34,41 +83,61 @@ async fn add_plugin_file(
         return Err(ApiError::Forbidden("Must be an admin to upload plugins".to_string()));
     }
 
-    match plugins.register_file(plugin.into_inner()).await {
+    let upload = upload.into_inner();
+    match
+        plugins.register_file(
+            upload.plugin,
+            upload.signature.map(|s| s.to_vec()),
+            upload.approved_hosts
+        ).await
+    {
         Ok(registered) => Ok(Json(registered.into())),
         Err(e) => Err(ApiError::internal(format!("Failed to register plugin: {e:?}")))
     }
 }
 
-#[post("/preview/file", data = "<plugin>")]
+#[derive(FromForm)]
+struct PluginPreviewUpload<'r> {
+    pub plugin: TempFile<'r>,
+
+    /// Detached ed25519 signature of the plugin, as raw bytes or base64
+    pub signature: Option<&'r [u8]>,
+}
+
+#[post("/preview/file", data = "<upload>")]
 async fn preview_plugin_file(
-    plugin: Form<TempFile<'_>>,
+    upload: Form<PluginPreviewUpload<'_>>,
     user: AuthUser,
     plugins: PluginRegistry
-) -> ApiResult<PluginMetadata> {
+) -> ApiResult<PluginPreview> {
     if user.kind != UserType::Admin {
         return Err(ApiError::Forbidden("Must be an admin to upload plugins".to_string()));
     }
+    let plugin = &upload.plugin;
     if plugin.content_type().is_some_and(|c| c.is_wasm()) {
         if let Ok(mut data) = plugin.open().await {
-            let res = plugins.preview_file(&mut data).await;
+            let res = plugins.preview_file(&mut data, upload.signature.map(|s| s.to_vec())).await;
             if let Ok(result) = res {
-                return Ok(Json(result.metadata));
+                Ok(Json(result.into()))
             } else {
                 println!("{res:?}");
-                return Err(ApiError::BadRequest("Plugin data was invalid.".to_string()));
+                Err(ApiError::BadRequest("Plugin data was invalid.".to_string()))
             }
         } else {
-            return Err(ApiError::BadRequest("File was unreadable.".to_string()));
+            Err(ApiError::BadRequest("File was unreadable.".to_string()))
         }
     } else {
-        return Err(ApiError::BadRequest("Expected a WASM file.".to_string()));
+        Err(ApiError::BadRequest("Expected a WASM file.".to_string()))
     }
 }
 
 #[derive(Deserialize)]
 struct PluginURL {
     pub url: String,
+
+    /// Hosts to approve, or none to keep the approvals of an already installed plugin
+    #[serde(default)]
+    pub approved_hosts: Option<Vec<String>>,
 }
 
 #[post("/add/url", data = "<plugin>")]
@@ -80,7 +149,12 @@ async fn add_plugin_url(
     if user.kind != UserType::Admin {
         return Err(ApiError::Forbidden("Must be an admin to upload plugins".to_string()));
     }
-    if let Ok(registered) = plugins.register_url(plugin.url.clone()).await {
+    if
+        let Ok(registered) = plugins.register_url(
+            plugin.url.clone(),
+            plugin.approved_hosts.clone()
+        ).await
+    {
         Ok(Json(registered.into()))
     } else {
         Err(ApiError::Internal("Failed to upload plugin".to_string()))
@@ -92,14 +166,14 @@ async fn preview_plugin_url(
     plugin: Json<PluginURL>,
     user: AuthUser,
     plugins: PluginRegistry
-) -> ApiResult<PluginMetadata> {
+) -> ApiResult<PluginPreview> {
     if user.kind != UserType::Admin {
         return Err(ApiError::Forbidden("Must be an admin to upload plugins".to_string()));
     }
     if let Ok(result) = plugins.preview_url(plugin.url.clone()).await {
-        return Ok(Json(result.metadata));
+        Ok(Json(result.into()))
     } else {
-        return Err(ApiError::BadRequest("Invalid plugin.".to_string()));
+        Err(ApiError::BadRequest("Invalid plugin.".to_string()))
     }
 }
 
@@ -123,8 +197,10 @@ async fn delete_plugin(user: AuthUser, id: &str, plugins: PluginRegistry) -> Api
     }
 
     if plugins.exists(id).await {
-        plugins.deregister(id).await;
-        Ok(Json(()))
+        match plugins.deregister(id).await {
+            Ok(_) => Ok(Json(())),
+            Err(e) => Err(ApiError::BadRequest(e.to_string())),
+        }
     } else {
         Err(ApiError::NotFound("Unknown plugin ID".to_string()))
     }
@@ -137,8 +213,11 @@ async fn enable_plugin(user: AuthUser, id: &str, plugins: PluginRegistry) -> Api
     }
 
     if let Some(mut plugin) = plugins.get(id).await {
+        if let Err(reason) = plugin.run_hook(LifecycleHook::Enable, (), CallScope::default()).await {
+            return Err(ApiError::BadRequest(reason));
+        }
         plugin.set_enabled(true);
-        if let Ok(_) = plugin.save().await {
+        if plugin.save().await.is_ok() {
             Ok(Json(()))
         } else {
             Err(ApiError::Internal("Failed to update registered plugin".to_string()))
@@ -155,8 +234,11 @@ async fn disable_plugin(user: AuthUser, id: &str, plugins: PluginRegistry) -> Ap
     }
 
     if let Some(mut plugin) = plugins.get(id).await {
+        if let Err(reason) = plugin.run_hook(LifecycleHook::Disable, (), CallScope::default()).await {
+            return Err(ApiError::BadRequest(reason));
+        }
         plugin.set_enabled(false);
-        if let Ok(_) = plugin.save().await {
+        if plugin.save().await.is_ok() {
             Ok(Json(()))
         } else {
             Err(ApiError::Internal("Failed to update registered plugin".to_string()))
@@ -181,6 +263,207 @@ async fn get_plugin(user: AuthUser, id: &str, plugins: PluginRegistry) -> ApiRes
     }
 }
 
+#[post("/<id>/limits", data = "<limits>")]
+async fn set_plugin_limits(
+    user: AuthUser,
+    id: &str,
+    plugins: PluginRegistry,
+    limits: Json<Option<PluginLimits>>
+) -> ApiResult<PluginInfo> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to modify plugin limits".to_string()));
+    }
+
+    if let Some(mut plugin) = plugins.get(id).await {
+        plugin.set_limits(limits.into_inner());
+        if plugin.save().await.is_ok() {
+            match plugins.reload(id).await {
+                Ok(reloaded) => Ok(Json(reloaded.into())),
+                Err(e) => Err(ApiError::internal(format!("Failed to reload plugin: {e:?}"))),
+            }
+        } else {
+            Err(ApiError::Internal("Failed to update registered plugin".to_string()))
+        }
+    } else {
+        Err(ApiError::NotFound("Unknown plugin ID".to_string()))
+    }
+}
+
+#[post("/updates/check")]
+async fn check_plugin_updates(user: AuthUser, plugins: PluginRegistry) -> ApiResult<Vec<PluginInfo>> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to check for plugin updates".to_string()));
+    }
+
+    match plugins.check_updates().await {
+        Ok(updated) => Ok(Json(updated)),
+        Err(e) => Err(ApiError::internal(format!("Failed to check for updates: {e}"))),
+    }
+}
+
+#[post("/<id>/update/check")]
+async fn check_plugin_update(
+    user: AuthUser,
+    id: &str,
+    plugins: PluginRegistry
+) -> ApiResult<Option<PluginUpdate>> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to check for plugin updates".to_string()));
+    }
+
+    if plugins.exists(id).await {
+        match plugins.check_update(id).await {
+            Ok(update) => Ok(Json(update)),
+            Err(e) => Err(ApiError::BadRequest(format!("Failed to check for updates: {e}"))),
+        }
+    } else {
+        Err(ApiError::NotFound("Unknown plugin ID".to_string()))
+    }
+}
+
+#[post("/<id>/update")]
+async fn apply_plugin_update(
+    user: AuthUser,
+    id: &str,
+    plugins: PluginRegistry
+) -> ApiResult<PluginInfo> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to update plugins".to_string()));
+    }
+
+    if plugins.exists(id).await {
+        match plugins.apply_update(id).await {
+            Ok(plugin) => Ok(Json(plugin.into())),
+            Err(e) => Err(ApiError::BadRequest(format!("Failed to update plugin: {e}"))),
+        }
+    } else {
+        Err(ApiError::NotFound("Unknown plugin ID".to_string()))
+    }
+}
+
+#[post("/<id>/versions/<version>/rollback")]
+async fn rollback_plugin(
+    user: AuthUser,
+    id: &str,
+    version: &str,
+    plugins: PluginRegistry
+) -> ApiResult<PluginInfo> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to roll back plugins".to_string()));
+    }
+
+    if plugins.exists(id).await {
+        match plugins.rollback(id, version).await {
+            Ok(plugin) => Ok(Json(plugin.into())),
+            Err(e) => Err(ApiError::BadRequest(format!("Failed to roll back plugin: {e}"))),
+        }
+    } else {
+        Err(ApiError::NotFound("Unknown plugin ID".to_string()))
+    }
+}
+
+#[post("/<id>/hosts", data = "<hosts>")]
+async fn set_plugin_hosts(
+    user: AuthUser,
+    id: &str,
+    plugins: PluginRegistry,
+    hosts: Json<Vec<String>>
+) -> ApiResult<PluginInfo> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to modify plugin hosts".to_string()));
+    }
+
+    if let Some(mut plugin) = plugins.get(id).await {
+        plugin.set_approved_hosts(hosts.into_inner());
+        if plugin.save().await.is_ok() {
+            match plugins.reload(id).await {
+                Ok(reloaded) => Ok(Json(reloaded.into())),
+                Err(e) => Err(ApiError::internal(format!("Failed to reload plugin: {e:?}"))),
+            }
+        } else {
+            Err(ApiError::Internal("Failed to update registered plugin".to_string()))
+        }
+    } else {
+        Err(ApiError::NotFound("Unknown plugin ID".to_string()))
+    }
+}
+
+#[get("/<id>/logs?<limit>&<call>")]
+async fn get_plugin_logs(
+    user: AuthUser,
+    id: &str,
+    plugins: PluginRegistry,
+    logs: Docs<PluginLog>,
+    limit: Option<i64>,
+    call: Option<&str>
+) -> ApiResult<Vec<PluginLog>> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to read plugin logs".to_string()));
+    }
+
+    if plugins.exists(id).await {
+        let mut filter = doc! { "plugin": id };
+        if let Some(call_id) = call {
+            filter.insert("call_id", call_id);
+        }
+
+        if let Ok(results) = PluginLog::recent(&logs, filter, limit.unwrap_or(100).clamp(1, 1000)).await {
+            Ok(Json(results))
+        } else {
+            Err(ApiError::Internal("Failed to list plugin logs".to_string()))
+        }
+    } else {
+        Err(ApiError::NotFound("Unknown plugin ID".to_string()))
+    }
+}
+
+#[get("/<id>/tasks")]
+async fn get_plugin_tasks(
+    user: AuthUser,
+    id: &str,
+    plugins: PluginRegistry,
+    runs: Docs<PluginTaskRun>
+) -> ApiResult<Vec<PluginTaskRun>> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to view plugin tasks".to_string()));
+    }
+
+    if plugins.exists(id).await {
+        if let Ok(results) = runs.query_many(doc! { "plugin": id }).await {
+            Ok(Json(results))
+        } else {
+            Err(ApiError::Internal("Failed to list plugin task runs".to_string()))
+        }
+    } else {
+        Err(ApiError::NotFound("Unknown plugin ID".to_string()))
+    }
+}
+
+#[get("/<id>/health?<refresh>")]
+async fn get_plugin_health(
+    user: AuthUser,
+    id: &str,
+    plugins: PluginRegistry,
+    refresh: Option<bool>
+) -> ApiResult<Option<PluginHealth>> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to check plugin health".to_string()));
+    }
+
+    if let Some(plugin) = plugins.get(id).await {
+        if refresh.unwrap_or(false) {
+            match plugins.check_health(id).await {
+                Ok(health) => Ok(Json(health)),
+                Err(e) => Err(ApiError::internal(format!("Failed to check plugin health: {e}"))),
+            }
+        } else {
+            Ok(Json(plugin.health()))
+        }
+    } else {
+        Err(ApiError::NotFound("Unknown plugin ID".to_string()))
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug)]
 struct PluginConfigModel {
     #[serde(default)]
@@ -189,6 +472,24 @@ struct PluginConfigModel {
     pub options: HashMap<String, Value>,
 }
 
+/// Gives the plugin a chance to check a configuration profile before it's stored.
+async fn run_config_hook(
+    plugin: &Plugin,
+    config: &PluginConfiguration,
+    created: bool
+) -> Result<(), String> {
+    plugin.run_hook(
+        LifecycleHook::ConfigSaved,
+        ConfigSavedParams {
+            config_id: config.id.to_string(),
+            name: config.name.clone(),
+            created,
+            plugin_config: config.options.clone().into(),
+        },
+        CallScope::config(config.id.clone())
+    ).await
+}
+
 #[post("/<id>/configs", data = "<conf>")]
 async fn create_plugin_config(
     user: AuthUser,
@@ -203,7 +504,7 @@ async fn create_plugin_config(
         );
     }
 
-    if plugins.exists(id).await {
+    if let Some(plugin) = plugins.get(id).await {
         if configs.exists(doc! { "name": conf.name.clone(), "plugin": id }).await {
             return Err(
                 ApiError::MethodNotAllowed(
@@ -220,8 +521,16 @@ async fn create_plugin_config(
             options: conf.options.clone(),
         };
 
-        if let Ok(_) = configs.save(config.clone()).await {
-            Ok(Json(config))
+        if let Err(reason) = run_config_hook(&plugin, &config, true).await {
+            return Err(ApiError::BadRequest(reason));
+        }
+
+        if configs.save(config.clone()).await.is_ok() {
+            // Host templates may reference this profile, so rebuild the plugin's allowlist
+            match plugins.reload(id).await {
+                Ok(_) => Ok(Json(config)),
+                Err(e) => Err(ApiError::internal(format!("Failed to reload plugin: {e:?}"))),
+            }
         } else {
             Err(ApiError::Internal("Failed to save config to database".to_string()))
         }
@@ -270,7 +579,7 @@ async fn get_plugin_config_by_id(
 
     if plugins.exists(id).await {
         if let Some(result) = configs.get(config_id).await {
-            if result.plugin == id.to_string() {
+            if result.plugin == id {
                 Ok(Json(result))
             } else {
                 Err(ApiError::NotFound("Unknown plugin config ID".to_string()))
@@ -296,7 +605,7 @@ async fn update_plugin_config(
         return Err(ApiError::Forbidden("Must be an admin to modify plugin configs".to_string()));
     }
 
-    if plugins.exists(id).await {
+    if let Some(plugin) = plugins.get(id).await {
         if
             configs.exists(
                 doc! { "name": update.name.clone(), "plugin": id, "_id": {"$ne": config_id} }
@@ -310,12 +619,18 @@ async fn update_plugin_config(
         }
 
         if let Some(mut result) = configs.get(config_id).await {
-            if result.plugin == id.to_string() {
+            if result.plugin == id {
                 result.icon = update.icon.clone();
                 result.name = update.name.clone();
                 result.options = update.options.clone();
-                if let Ok(_) = configs.save(result.clone()).await {
-                    Ok(Json(result))
+                if let Err(reason) = run_config_hook(&plugin, &result, false).await {
+                    return Err(ApiError::BadRequest(reason));
+                }
+                if configs.save(result.clone()).await.is_ok() {
+                    match plugins.reload(id).await {
+                        Ok(_) => Ok(Json(result)),
+                        Err(e) => Err(ApiError::internal(format!("Failed to reload plugin: {e:?}"))),
+                    }
                 } else {
                     Err(ApiError::Internal("Failed to save config to database".to_string()))
                 }
@@ -344,9 +659,12 @@ async fn delete_plugin_config(
 
     if plugins.exists(id).await {
         if let Some(result) = configs.get(config_id).await {
-            if result.plugin == id.to_string() {
-                if let Ok(_) = configs.delete_one(doc! { "_id": config_id }).await {
-                    Ok(Json(()))
+            if result.plugin == id {
+                if configs.delete_one(doc! { "_id": config_id }).await.is_ok() {
+                    match plugins.reload(id).await {
+                        Ok(_) => Ok(Json(())),
+                        Err(e) => Err(ApiError::internal(format!("Failed to reload plugin: {e:?}"))),
+                    }
                 } else {
                     Err(ApiError::Internal("Failed to save config to database".to_string()))
                 }
@@ -421,18 +739,109 @@ async fn get_one_validated_config(
     }
 }
 
-#[allow(unused_variables)]
+/// Outcome of testing a configuration profile. `live` is only set when the profile passed field
+/// validation and the plugin exports `test_config`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ConfigTestReport {
+    pub success: bool,
+    pub validation: ValidationResult,
+    pub live: Option<ConfigTestResult>,
+    pub fields: HashMap<String, Vec<String>>,
+}
+
+#[post("/<id>/configs/<config_id>/test")]
+async fn test_plugin_config(
+    user: AuthUser,
+    id: &str,
+    config_id: &str,
+    plugins: PluginRegistry,
+    configs: Docs<PluginConfiguration>
+) -> ApiResult<ConfigTestReport> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to test plugin configs".to_string()));
+    }
+
+    if let Some(plugin) = plugins.get(id).await {
+        if let Some(conf) = configs.query_one(doc! {"_id": config_id, "plugin": id}).await {
+            let validation = plugin.metadata().config.validate(conf.options.clone());
+            let mut fields: HashMap<String, Vec<String>> = HashMap::new();
+            for (key, arg) in validation.arguments.iter().filter(|(_, a)| !a.valid) {
+                fields
+                    .entry(key.clone())
+                    .or_default()
+                    .push(
+                        (if arg.previous.is_some() {
+                            "Value is invalid"
+                        } else {
+                            "Required value is missing"
+                        }).to_string()
+                    );
+            }
+
+            let live = if validation.valid && plugin.exports(TEST_CONFIG_EXPORT) {
+                let params = ConfigTestParams {
+                    config_id: conf.id.to_string(),
+                    name: conf.name.clone(),
+                    plugin_config: validation.values().into(),
+                };
+                Some(
+                    match
+                        plugin.call_scoped::<_, ConfigTestResult>(
+                            TEST_CONFIG_EXPORT,
+                            params,
+                            CallScope::config(conf.id.clone())
+                        ).await
+                    {
+                        Ok(result) => result,
+                        Err((e, _)) => ConfigTestResult::failure(e.to_string()),
+                    }
+                )
+            } else {
+                None
+            };
+
+            if let Some(result) = &live {
+                for (key, problem) in &result.fields {
+                    fields.entry(key.clone()).or_default().push(problem.clone());
+                }
+            }
+
+            Ok(
+                Json(ConfigTestReport {
+                    success: validation.valid && live.as_ref().is_none_or(|r| r.success),
+                    validation,
+                    live,
+                    fields,
+                })
+            )
+        } else {
+            Err(ApiError::NotFound("Unknown config ID".to_string()))
+        }
+    } else {
+        Err(ApiError::NotFound("Unknown plugin ID".to_string()))
+    }
+}
+
 #[post("/<id>/call", data = "<parameters>")]
+#[allow(clippy::too_many_arguments)]
 async fn call_plugin_method(
     id: &str,
-    user: AuthUser,
+    user: Option<AuthUser>,
+    code: InviteCode,
     plugins: PluginRegistry,
     parameters: Json<MethodCall>,
     configs: Docs<PluginConfiguration>,
-    services: Docs<Service>
+    services: Docs<Service>,
+    invites: Docs<Invite>,
+    usages: Docs<InviteUsage>
 ) -> ApiResult<MethodResult> {
     if let Some(plugin) = plugins.get(id).await {
-        match parameters.into_inner() {
+        let parameters = parameters.into_inner();
+        if user.is_none() && !matches!(parameters, MethodCall::InviteDefinedField { .. }) {
+            return Err(ApiError::AuthenticationRequired("Must be authenticated".to_string()));
+        }
+
+        match parameters {
             MethodCall::PluginDefinedField { field_key } => {
                 if let Some(field) = plugin.get_field(field_key) {
                     if let FieldType::PluginDefined { method, context, .. } = field.field {
@@ -486,9 +895,13 @@ async fn call_plugin_method(
                     }
 
                     match
-                        plugin.call::<_, FieldType>(method, PluginFieldParams::ServiceConfig {
-                            plugin_config: config.options.into(),
-                        }).await
+                        plugin.call_scoped::<_, FieldType>(
+                            method,
+                            PluginFieldParams::ServiceConfig {
+                                plugin_config: config.options.into(),
+                            },
+                            CallScope::config(config.id.clone())
+                        ).await
                     {
                         Ok(r) => Ok(Json(MethodReply::FieldDefinition(r).into())),
                         Err(e) => Ok(Json(e.into())),
@@ -497,7 +910,85 @@ async fn call_plugin_method(
                     Err(ApiError::MethodNotAllowed("Field is not plugin-defined".to_string()))
                 }
             }
-            _ => Err(ApiError::Internal("Selected method is not yet handled.".to_string())),
+            MethodCall::InviteDefinedField { field_key, invite_id, service_id, grant_id } => {
+                let invite = invites
+                    .get(invite_id.clone()).await
+                    .ok_or(
+                        Err::<(), ApiError>(ApiError::NotFound("Unknown invite ID".to_string()))
+                    )?;
+                if !user.is_some_and(|u| u.kind == UserType::Admin) {
+                    let uses = usages
+                        .count_documents(doc! { "invite_id": invite_id.clone() }).await
+                        .or(Err(ApiError::Internal("Failed to retrieve invite usages".to_string())))?;
+                    if
+                        code.0.is_none_or(|c| c != invite.code) ||
+                        invite.is_expired(uses)
+                    {
+                        return Err(ApiError::NotFound("Unknown invite ID".to_string()));
+                    }
+                }
+                if !invite.services.contains(&service_id.clone().into()) {
+                    return Err(ApiError::NotFound("Unknown service ID".to_string()));
+                }
+
+                let service = services
+                    .get(service_id).await
+                    .ok_or(
+                        Err::<(), ApiError>(ApiError::NotFound("Unknown service ID".to_string()))
+                    )?;
+                if
+                    let Some(
+                        ServiceGrant::Grant { plugin_id, config_id, grant_id: grant_key, options, .. },
+                    ) = service.get_grant(grant_id)
+                {
+                    if plugin_id.to_string() != plugin.id() {
+                        return Err(ApiError::NotFound("Unknown grant ID".to_string()));
+                    }
+
+                    let config = configs
+                        .get(config_id.to_string()).await
+                        .ok_or(
+                            Err::<(), ApiError>(ApiError::NotFound("Unknown config ID".to_string()))
+                        )?;
+                    let grant = plugin
+                        .get_grant(grant_key)
+                        .ok_or(
+                            Err::<(), ApiError>(ApiError::NotFound("Unknown grant ID".to_string()))
+                        )?;
+                    let field = grant
+                        .get_argument(field_key)
+                        .ok_or(
+                            Err::<(), ApiError>(ApiError::NotFound("Unknown field key".to_string()))
+                        )?;
+                    if let FieldType::PluginDefined { method, context, .. } = field.field {
+                        if !matches!(context, PluginDefinedMethodContext::Invite) {
+                            return Err(
+                                ApiError::MethodNotAllowed(
+                                    "Field is used in the wrong context".to_string()
+                                )
+                            );
+                        }
+
+                        match
+                            plugin.call_scoped::<_, FieldType>(
+                                method,
+                                PluginFieldParams::InviteConfig {
+                                    plugin_config: config.options.into(),
+                                    service_config: options.into(),
+                                },
+                                CallScope::config(config.id.clone())
+                            ).await
+                        {
+                            Ok(r) => Ok(Json(MethodReply::FieldDefinition(r).into())),
+                            Err(e) => Ok(Json(e.into())),
+                        }
+                    } else {
+                        Err(ApiError::MethodNotAllowed("Field is not plugin-defined".to_string()))
+                    }
+                } else {
+                    Err(ApiError::NotFound("Unknown grant ID".to_string()))
+                }
+            }
         }
     } else {
         Err(ApiError::NotFound("Unknown plugin ID".to_string()))
@@ -505,7 +996,7 @@ async fn call_plugin_method(
 }
 
 pub fn routes() -> Vec<Route> {
-    return routes![
+    routes![
         add_plugin_file,
         add_plugin_url,
         list_plugins,
@@ -515,6 +1006,15 @@ pub fn routes() -> Vec<Route> {
         enable_plugin,
         disable_plugin,
         get_plugin,
+        set_plugin_limits,
+        set_plugin_hosts,
+        rollback_plugin,
+        check_plugin_updates,
+        check_plugin_update,
+        apply_plugin_update,
+        get_plugin_logs,
+        get_plugin_health,
+        get_plugin_tasks,
         create_plugin_config,
         get_plugin_configs,
         get_plugin_config_by_id,
@@ -522,6 +1022,7 @@ pub fn routes() -> Vec<Route> {
         delete_plugin_config,
         get_validated_configs,
         call_plugin_method,
-        get_one_validated_config
-    ];
+        get_one_validated_config,
+        test_plugin_config
+    ]
 }
@@ -12,7 +12,7 @@ use crate::{
     models::{
         auth::{ AuthUser, UserType },
         error::ApiError,
-        plugin::{ PluginConfiguration, PluginRegistry },
+        plugin::{ CallScope, PluginConfiguration, PluginRegistry },
         service::{ Service, ServiceGrant },
     },
     util::{ database::Docs, ApiResult },
@@ -39,7 +39,7 @@ async fn create_service(
         model.icon.clone(),
         model.description.clone()
     );
-    if let Ok(_) = services.save(created.clone()).await {
+    if services.save(created.clone()).await.is_ok() {
         Ok(Json(created))
     } else {
         Err(ApiError::Internal("Failed to create new Service".to_string()))
@@ -85,7 +85,7 @@ async fn delete_service(user: AuthUser, services: Docs<Service>, id: &str) -> Ap
     if user.kind != UserType::Admin {
         return Err(ApiError::Forbidden("Must be an admin to delete services".to_string()));
     }
-    if let Ok(_) = services.delete_one(doc! { "_id": id }).await {
+    if services.delete_one(doc! { "_id": id }).await.is_ok() {
         Ok(Json(()))
     } else {
         Err(ApiError::NotFound("Unknown service ID".to_string()))
@@ -106,7 +106,7 @@ async fn update_service(
         result.description = model.description.clone();
         result.icon = model.icon.clone();
         result.name = model.name.clone();
-        if let Ok(_) = services.save(result.clone()).await {
+        if services.save(result.clone()).await.is_ok() {
             Ok(Json(result))
         } else {
             Err(ApiError::Internal("Failed to update service".to_string()))
@@ -128,7 +128,7 @@ async fn create_service_grant(
     }
     if let Some(mut result) = services.get(id).await {
         result.add_grant(grant.into_inner());
-        if let Ok(_) = services.save(result.clone()).await {
+        if services.save(result.clone()).await.is_ok() {
             Ok(Json(result))
         } else {
             Err(ApiError::Internal("Failed to update service".to_string()))
@@ -150,9 +150,9 @@ async fn update_service_grant(
         return Err(ApiError::Forbidden("Must be an admin to edit services".to_string()));
     }
     if let Some(mut result) = services.get(id).await {
-        if result.grants.contains_key(&grant_id.to_string()) {
+        if result.grants.contains_key(grant_id) {
             result.modify_grant(grant_id, grant.into_inner());
-            if let Ok(_) = services.save(result.clone()).await {
+            if services.save(result.clone()).await.is_ok() {
                 Ok(Json(result))
             } else {
                 Err(ApiError::Internal("Failed to update service".to_string()))
@@ -197,9 +197,9 @@ async fn delete_service_grant(
         return Err(ApiError::Forbidden("Must be an admin to edit services".to_string()));
     }
     if let Some(mut result) = services.get(id).await {
-        if result.grants.contains_key(&grant_id.to_string()) {
+        if result.grants.contains_key(grant_id) {
             result.remove_grant(grant_id);
-            if let Ok(_) = services.save(result.clone()).await {
+            if services.save(result.clone()).await.is_ok() {
                 Ok(Json(result))
             } else {
                 Err(ApiError::Internal("Failed to update service".to_string()))
@@ -290,7 +290,7 @@ async fn test_grant(
                     if let Some(action) = plugin.get_grant(grant_key) {
                         if let Some(config) = configs.get(config_id).await {
                             match
-                                plugin.call::<_, Vec<GrantResource>>(
+                                plugin.call_scoped::<_, Vec<GrantResource>>(
                                     action.method.clone(),
                                     GrantActionParams {
                                         dry_run: test.dry_run,
@@ -298,7 +298,8 @@ async fn test_grant(
                                         plugin_config: config.options.clone().into(),
                                         service_config: options.clone().into(),
                                         user_arguments: test.arguments.clone(),
-                                    }
+                                    },
+                                    CallScope::config(config.id.clone())
                                 ).await
                             {
                                 Ok(resources) => Ok(Json(resources)),
@@ -330,7 +331,7 @@ async fn test_grant(
 }
 
 pub fn routes() -> Vec<Route> {
-    return routes![
+    routes![
         create_service,
         get_services,
         get_service_by_id,
@@ -342,5 +343,5 @@ pub fn routes() -> Vec<Route> {
         get_service_grant,
         validate_plugin_grant,
         test_grant
-    ];
+    ]
 }
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use bson::doc;
+use invex_sdk::call::ActionResult;
+use rocket::{ serde::json::Json, Route };
+use serde_json::Value;
+
+use crate::{
+    models::{
+        auth::{ AuthUser, UserType },
+        error::ApiError,
+        invite::{ GrantRevocation, InviteUsage, UsageAdminActions },
+        plugin::{ PluginLog, PluginRegistry },
+    },
+    util::{ database::{ Collections, Docs }, ApiResult },
+};
+
+#[post("/<id>/<service_id>/<grant_id>/revoke")]
+async fn revoke_usage_grant(
+    user: AuthUser,
+    usages: Docs<InviteUsage>,
+    plugins: PluginRegistry,
+    collections: Collections,
+    id: &str,
+    service_id: &str,
+    grant_id: &str
+) -> ApiResult<GrantRevocation> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to revoke granted resources".to_string()));
+    }
+
+    if let Some(mut usage) = usages.get(id).await {
+        Ok(Json(usage.revoke(service_id, grant_id, user.id.clone(), &collections, &plugins).await?))
+    } else {
+        Err(ApiError::NotFound("Unknown invite usage ID".to_string()))
+    }
+}
+
+#[get("/<id>/actions")]
+async fn get_usage_admin_actions(
+    user: AuthUser,
+    usages: Docs<InviteUsage>,
+    plugins: PluginRegistry,
+    collections: Collections,
+    id: &str
+) -> ApiResult<Vec<UsageAdminActions>> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to list admin actions".to_string()));
+    }
+
+    if let Some(usage) = usages.get(id).await {
+        Ok(Json(usage.admin_actions(&collections, &plugins).await))
+    } else {
+        Err(ApiError::NotFound("Unknown invite usage ID".to_string()))
+    }
+}
+
+#[post("/<id>/<service_id>/<grant_id>/<resource_id>/actions/<action>", data = "<arguments>")]
+#[allow(clippy::too_many_arguments)]
+async fn run_usage_admin_action(
+    user: AuthUser,
+    usages: Docs<InviteUsage>,
+    plugins: PluginRegistry,
+    collections: Collections,
+    id: &str,
+    service_id: &str,
+    grant_id: &str,
+    resource_id: &str,
+    action: &str,
+    arguments: Json<HashMap<String, Value>>
+) -> ApiResult<ActionResult> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to run admin actions".to_string()));
+    }
+
+    if let Some(mut usage) = usages.get(id).await {
+        Ok(
+            Json(
+                usage.run_admin_action(
+                    service_id,
+                    grant_id,
+                    resource_id,
+                    action,
+                    arguments.into_inner(),
+                    &collections,
+                    &plugins
+                ).await?
+            )
+        )
+    } else {
+        Err(ApiError::NotFound("Unknown invite usage ID".to_string()))
+    }
+}
+
+#[get("/<id>/<service_id>/<grant_id>/logs")]
+async fn get_usage_grant_logs(
+    user: AuthUser,
+    usages: Docs<InviteUsage>,
+    logs: Docs<PluginLog>,
+    id: &str,
+    service_id: &str,
+    grant_id: &str
+) -> ApiResult<Vec<PluginLog>> {
+    if user.kind != UserType::Admin {
+        return Err(ApiError::Forbidden("Must be an admin to read plugin logs".to_string()));
+    }
+
+    let usage = usages.get(id).await.ok_or(ApiError::not_found("Unknown invite usage ID"))?;
+    let call_id = usage
+        .get_grant(service_id)
+        .and_then(|g| g.calls.get(grant_id).cloned())
+        .ok_or(ApiError::not_found("No plugin call recorded for this grant"))?;
+    match PluginLog::recent(&logs, doc! { "call_id": call_id }, 500).await {
+        Ok(results) => Ok(Json(results)),
+        Err(_) => Err(ApiError::Internal("Failed to list plugin logs".to_string())),
+    }
+}
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        revoke_usage_grant,
+        get_usage_admin_actions,
+        run_usage_admin_action,
+        get_usage_grant_logs
+    ]
+}
@@ -107,7 +107,7 @@ async fn create_user(
             new_user.password.clone(),
         ),
     } {
-        if let Ok(_) = users.save(created.clone()).await {
+        if users.save(created.clone()).await.is_ok() {
             Ok(Json(created.into()))
         } else {
             Err(ApiError::Internal("Failed to store new user".to_string()))
@@ -138,5 +138,5 @@ async fn delete_user(user_id: String, user: AuthUser, users: Docs<AuthUser>) ->
 }
 
 pub fn routes() -> Vec<Route> {
-    return routes![list_users, create_user, delete_user];
+    routes![list_users, create_user, delete_user]
 }
@@ -1,16 +1,16 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use bson::doc;
 use controllers::apply_routes;
 use extism::set_log_callback;
-use models::{auth::{AuthUser, SessionFairing, UserType}, plugin::{PluginRegistry, RegisteredPlugin}};
-use mongodb::{Database, IndexModel};
+use models::{auth::{AuthUser, SessionFairing, UserType}, invite::{ArgumentUpload, InviteUsage}, plugin::{PluginConfiguration, PluginKv, PluginLog, PluginRegistry, PluginTaskRun, RegisteredPlugin}};
+use mongodb::{options::IndexOptions, Database, IndexModel};
 use rocket::{fairing::AdHoc, futures::TryStreamExt, Config as RocketConfig};
-use tokio::sync::Mutex;
+use tokio::sync::{mpsc::unbounded_channel, Mutex};
 mod config;
 
 use config::Config;
-use util::{database::{Docs, Document, Fs}, PluginRegistryMap};
+use util::{database::{Docs, Document, Fs}, logs, scheduler::Scheduler, PluginRegistryMap};
 
 #[macro_use]
 extern crate rocket;
@@ -22,7 +22,8 @@ mod util;
 #[launch]
 async fn rocket() -> _ {
     let conf: Config = RocketConfig::figment().extract_inner("app").expect("App config");
-    set_log_callback(|s| println!("{}", s.trim_end()), format!("extism={}", conf.plugin_logging)).expect("Failed to set logging callback.");
+    let (log_sender, log_receiver) = unbounded_channel::<PluginLog>();
+    set_log_callback(move |s| logs::capture(s, &log_sender), format!("extism={}", conf.plugin_logging)).expect("Failed to set logging callback.");
     let rocket = apply_routes(rocket::build());
     
     rocket
@@ -39,6 +40,40 @@ async fn rocket() -> _ {
         .attach(AdHoc::on_liftoff("Setup Database", |rocket| Box::pin(async move {
             let users = Docs::<AuthUser>::new(rocket.state::<Database>().expect("Database not initialized").clone());
             let _ = users.create_index(IndexModel::builder().keys(doc! {"username": "text", "email": "text"}).build()).await.expect("Failed to create index on users");
+            let kv = Docs::<PluginKv>::new(rocket.state::<Database>().expect("Database not initialized").clone());
+            let _ = kv.create_index(IndexModel::builder().keys(doc! {"plugin": 1, "config": 1, "key": 1}).options(IndexOptions::builder().unique(true).build()).build()).await.expect("Failed to create index on plugin KV entries");
+            let uploads = Docs::<ArgumentUpload>::new(rocket.state::<Database>().expect("Database not initialized").clone());
+            let _ = uploads.create_index(IndexModel::builder().keys(doc! {"uploaded_at": 1}).options(IndexOptions::builder().expire_after(ArgumentUpload::TTL).build()).build()).await.expect("Failed to create index on argument uploads");
+        })))
+        .attach(AdHoc::on_liftoff("Store Plugin Logs", |rocket| Box::pin(async move {
+            let db = rocket.state::<Database>().expect("Database not initialized").clone();
+            let config = rocket.state::<Config>().expect("Config not initialized");
+            logs::create_collection(&db, config.plugin_log_size).await;
+            tokio::spawn(logs::persist(log_receiver, Docs::<PluginLog>::new(db)));
+        })))
+        .attach(AdHoc::on_liftoff("Upgrade Legacy Password Hashes", |rocket| Box::pin(async move {
+            let usages = Docs::<InviteUsage>::new(rocket.state::<Database>().expect("Database not initialized").clone());
+            tokio::spawn(async move {
+                let mut upgraded = 0;
+                for usage in usages.query_many(doc! {}).await.unwrap_or_default() {
+                    for (path, old, new) in usage.password_upgrades() {
+                        let (Ok(old), Ok(new)) = (bson::to_bson(&old), bson::to_bson(&new)) else {
+                            continue;
+                        };
+                        // Only touch the password itself, and only if nothing changed it since it was read
+                        let updated = usages.update_one(
+                            doc! { "_id": usage.id(), path.clone(): old },
+                            doc! { "$set": { path: new } }
+                        ).await;
+                        if updated.is_ok_and(|r| r.modified_count > 0) {
+                            upgraded += 1;
+                        }
+                    }
+                }
+                if upgraded > 0 {
+                    println!("Upgraded {upgraded} legacy password hashes");
+                }
+            });
         })))
         .attach(AdHoc::on_liftoff("Create Admin User",|rocket| Box::pin(async move {
             let users = Docs::<AuthUser>::new(rocket.state::<Database>().expect("Database not initialized").clone());
@@ -59,10 +94,33 @@ async fn rocket() -> _ {
             let plugins = rocket.state::<PluginRegistryMap>().expect("Plugins not initialized").clone();
             let plugins_db = Docs::<RegisteredPlugin>::new(rocket.state::<Database>().expect("Database not initialized").clone());
             let fs = Fs::from_db(rocket.state::<Database>().expect("Database not initialized"));
-            let registry = PluginRegistry::new(plugins.clone(), plugins_db.clone(), fs.clone());
+            let config = rocket.state::<Config>().expect("Config not initialized");
+            let configs_db = Docs::<PluginConfiguration>::new(rocket.state::<Database>().expect("Database not initialized").clone());
+            let kv_db = Docs::<PluginKv>::new(rocket.state::<Database>().expect("Database not initialized").clone());
+            let runs_db = Docs::<PluginTaskRun>::new(rocket.state::<Database>().expect("Database not initialized").clone());
+            let registry = PluginRegistry::new(plugins.clone(), plugins_db.clone(), configs_db.clone(), kv_db, fs.clone(), config.clone());
             let existing = plugins_db.find(doc! {}).await.expect("Failed to fetch from DB").try_collect::<Vec<RegisteredPlugin>>().await.expect("Failed to collect results");
             for plugin in existing {
                 registry.register_existing(plugin).await.expect("Failed to register existing plugin");
             }
+
+            let interval: Duration = config.plugin_update_interval.into();
+            let updater = registry.clone();
+            tokio::spawn(async move {
+                loop {
+                    tokio::time::sleep(interval).await;
+                    let _ = updater.check_updates().await;
+                }
+            });
+
+            tokio::spawn(Scheduler::new(registry.clone(), plugins_db.clone(), configs_db, runs_db).run());
+
+            let health_interval: Duration = config.plugin_health_interval.into();
+            tokio::spawn(async move {
+                loop {
+                    let _ = registry.check_all_health().await;
+                    tokio::time::sleep(health_interval).await;
+                }
+            });
         })))
 }
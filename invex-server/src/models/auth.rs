@@ -1,4 +1,4 @@
-use std::error::Error;
+use std::{error::Error, fmt::Display};
 
 use bevy_reflect::Reflect;
 use chrono::{DateTime, TimeDelta, Utc};
@@ -90,12 +90,12 @@ pub enum UserType {
     Admin
 }
 
-impl ToString for UserType {
-    fn to_string(&self) -> String {
-        match self {
-            UserType::Admin => String::from("admin"),
-            UserType::User => String::from("user")
-        }
+impl Display for UserType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            UserType::Admin => "admin",
+            UserType::User => "user"
+        })
     }
 }
 
@@ -129,9 +129,9 @@ impl AuthUser {
     }
 }
 
-impl Into<ClientUser> for AuthUser {
-    fn into(self) -> ClientUser {
-        ClientUser {kind: self.kind.clone(), id: self.id.clone(), email: self.email.clone(), username: self.username.clone()}
+impl From<AuthUser> for ClientUser {
+    fn from(val: AuthUser) -> Self {
+        ClientUser {kind: val.kind.clone(), id: val.id.clone(), email: val.email.clone(), username: val.username.clone()}
     }
 }
 
@@ -1,5 +1,6 @@
 use anyhow::Error;
 use bson::doc;
+use chrono::{DateTime, Utc};
 use invex_sdk::GrantResource;
 use serde::{Deserialize, Serialize};
 
@@ -39,6 +40,10 @@ pub enum ClientResourcePluginGrant {
         code: i32,
         reason: String,
     },
+    Revoked {
+        id: String,
+        revoked_at: DateTime<Utc>,
+    },
     Success {
         id: String,
         resources: Vec<GrantResource>,
@@ -53,6 +58,7 @@ pub enum ClientResourcePluginGrant {
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(tag = "type", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ClientResourceGrant {
     UnknownPlugin {
         id: String,
@@ -114,7 +120,8 @@ impl ClientResource {
         let mut result: Vec<Self> = Vec::new();
         for (id, service) in service_ids.iter().zip(services) {
             if let Some(index) = service_ids.iter().position(|i| *i == id.clone()) {
-                let grant_result = usage.grants[index.clone()].resources.clone();
+                let grant_result = usage.grants[index].resources.clone();
+                let revoked = usage.grants[index].revoked.clone();
                 let service_info = ClientResourceService {
                     index,
                     id: id.clone(),
@@ -168,15 +175,17 @@ impl ClientResource {
                                         grant_id: grant_id.to_string(),
                                         url,
                                         help,
-                                        result: match grant_result.clone() {
+                                        result: if let Some(revocation) = revoked.get(&sv_grant_id) {
+                                            ClientResourcePluginGrant::Revoked { id: sv_grant_id.clone(), revoked_at: revocation.revoked_at }
+                                        } else { match grant_result.clone() {
                                             GrantResult::Success { value } => value.get(&sv_grant_id).map_or(ClientResourcePluginGrant::GrantFailure { id: sv_grant_id.clone(), code: 404, reason: String::from("Unknown grant ID (may have been added post-redeem)") }, |val| {
                                                 match val {
                                                     GrantResult::Success { value } => ClientResourcePluginGrant::Success { id: sv_grant_id.clone(), resources: value.clone(), plugin_id: plugin_id.to_string(), plugin_name: plugin.info().metadata.name.clone(), plugin_icon: plugin.info().metadata.icon.clone(), grant_id: grant_id.to_string(), grant_name: grant.label.clone(), grant_icon: grant.icon.clone() },
-                                                    GrantResult::Error { code, reason } => ClientResourcePluginGrant::GrantFailure { id: sv_grant_id.clone(), code: code.clone(), reason: reason.clone() }
+                                                    GrantResult::Error { code, reason } => ClientResourcePluginGrant::GrantFailure { id: sv_grant_id.clone(), code: *code, reason: reason.clone() }
                                                 }
                                             }),
                                             GrantResult::Error { code, reason } => ClientResourcePluginGrant::ServiceFailure { code, reason }
-                                        }
+                                        } }
                                     }
                                 } else {
                                     ClientResourceGrant::UnknownPlugin { id: id.clone() }
@@ -1,14 +1,31 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{collections::HashMap, fmt::Debug, time::Duration};
 
+use anyhow::Error;
 use bevy_reflect::Reflect;
 use chrono::{ DateTime, Utc };
 use invex_macros::Document;
-use invex_sdk::GrantResource;
+use bson::doc;
+use invex_sdk::{
+    call::ActionResult,
+    params::{ AdminActionParams, GrantRevokeParams, ParameterMap, ResourceActionParams },
+    AdminAction,
+    ArgValidator,
+    FieldType,
+    GrantAction,
+    GrantResource,
+    HashedPassword,
+    PluginArgument,
+};
 use serde::{ de::DeserializeOwned, Deserialize, Serialize };
+use serde_json::Value;
 
-use crate::util::database::Id;
+use crate::util::database::{ Collections, Docs, Id };
 
-use super::error::ApiError;
+use super::{
+    error::ApiError,
+    plugin::{ CallScope, Plugin, PluginConfiguration, PluginRegistry },
+    service::{ Service, ServiceGrant },
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug, Reflect)]
 #[serde(rename_all = "snake_case")]
@@ -43,15 +60,23 @@ impl Invite {
         match &self.expires {
             Some(exp) =>
                 match exp {
-                    Expiration::Uses(uses) => ResolvedExpiration::Uses(uses.clone()),
+                    Expiration::Uses(uses) => ResolvedExpiration::Uses(*uses),
                     Expiration::Datetime(timestamp) =>
                         ResolvedExpiration::Datetime(
-                            DateTime::from_timestamp_millis(timestamp.clone()).unwrap()
+                            DateTime::from_timestamp_millis(*timestamp).unwrap()
                         ),
                 }
             None => ResolvedExpiration::Never,
         }
     }
+
+    pub fn is_expired(&self, uses: u64) -> bool {
+        match self.expires() {
+            ResolvedExpiration::Never => false,
+            ResolvedExpiration::Datetime(datetime) => Utc::now() > datetime,
+            ResolvedExpiration::Uses(max_uses) => uses >= max_uses,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -73,10 +98,47 @@ impl<T: Serialize + DeserializeOwned + Clone + Debug> From<Result<T, ApiError>>
     }
 }
 
+impl<T> From<ApiError> for GrantResult<T> {
+    fn from(value: ApiError) -> Self {
+        let (reason, code) = value.contents();
+        Self::Error { code, reason }
+    }
+}
+
+impl<T> From<(Error, i32)> for GrantResult<T> {
+    fn from(value: (Error, i32)) -> Self {
+        Self::Error { code: value.1, reason: value.0.to_string() }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GrantRevocation {
+    pub revoked_by: Id,
+    pub revoked_at: DateTime<Utc>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct InviteGrant {
     pub service: Id,
     pub resources: GrantResult<HashMap<String, GrantResult<Vec<GrantResource>>>>,
+
+    #[serde(default)]
+    pub revoked: HashMap<String, GrantRevocation>,
+
+    /// Plugin call ID of each grant's action, for looking up the logs of a redemption
+    #[serde(default)]
+    pub calls: HashMap<String, Id>,
+}
+
+impl InviteGrant {
+    pub fn get_resources(&self, grant_id: impl AsRef<str>) -> Option<Vec<GrantResource>> {
+        if let GrantResult::Success { value } = &self.resources {
+            if let Some(GrantResult::Success { value: resources }) = value.get(grant_id.as_ref()) {
+                return Some(resources.clone());
+            }
+        }
+        None
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Reflect, Document)]
@@ -91,3 +153,547 @@ pub struct InviteUsage {
     #[reflect(ignore)]
     pub grants: Vec<InviteGrant>,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UsageAdminActions {
+    pub service_id: String,
+    pub grant_id: String,
+    pub resource: GrantResource,
+    pub actions: Vec<AdminAction>,
+}
+
+#[derive(Clone)]
+pub struct UsageGrantContext {
+    pub plugin: Plugin,
+    pub action: GrantAction,
+    pub config: PluginConfiguration,
+    pub options: HashMap<String, Value>,
+    pub resources: Vec<GrantResource>,
+}
+
+impl InviteUsage {
+    /// Rehashes any unsalted SHA-512 account passwords left by older plugins, returning the document
+    /// path of each one along with its old and new hash.
+    pub fn password_upgrades(&self) -> Vec<(String, HashedPassword, HashedPassword)> {
+        let mut upgrades = Vec::new();
+        for (grant_index, grant) in self.grants.iter().enumerate() {
+            if let GrantResult::Success { value } = &grant.resources {
+                for (grant_id, result) in value.iter() {
+                    if let GrantResult::Success { value: resources } = result {
+                        for (index, resource) in resources.iter().enumerate() {
+                            if let GrantResource::Account { password: Some(password), .. } = resource {
+                                if let Some(new) = password.upgrade() {
+                                    upgrades.push((
+                                        format!("grants.{grant_index}.resources.value.{grant_id}.value.{index}.password"),
+                                        password.clone(),
+                                        new,
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        upgrades
+    }
+
+    pub fn get_grant(&self, service_id: impl AsRef<str>) -> Option<InviteGrant> {
+        self.grants
+            .iter()
+            .find(|g| g.service.to_string() == service_id.as_ref())
+            .cloned()
+    }
+
+    fn grant_index(&self, service_id: impl AsRef<str>) -> Result<usize, ApiError> {
+        self.grants
+            .iter()
+            .position(|g| g.service.to_string() == service_id.as_ref())
+            .ok_or(ApiError::not_found("Service not granted by this usage"))
+    }
+
+    /// Document path of a grant's revocation within the grant at `index`.
+    fn revocation_path(index: usize, grant_id: &str) -> String {
+        format!("grants.{index}.revoked.{grant_id}")
+    }
+
+    pub async fn resolve_grant(
+        &self,
+        service_id: impl AsRef<str>,
+        grant_id: impl AsRef<str>,
+        collections: &Collections,
+        plugins: &PluginRegistry
+    ) -> Result<UsageGrantContext, ApiError> {
+        let index = self.grant_index(service_id.as_ref())?;
+        if self.grants[index].revoked.contains_key(grant_id.as_ref()) {
+            return Err(ApiError::method_not_allowed("Grant has been revoked"));
+        }
+        let resources = self.grants[index]
+            .get_resources(grant_id.as_ref())
+            .ok_or(ApiError::bad_request("Grant was not successfully redeemed"))?;
+
+        let service = collections
+            .get::<Service>()
+            .get(service_id.as_ref()).await
+            .ok_or(ApiError::not_found("Unknown service ID"))?;
+        if
+            let Some(ServiceGrant::Grant { plugin_id, config_id, grant_id: grant_key, options, .. }) =
+                service.get_grant(grant_id.as_ref())
+        {
+            let plugin = plugins
+                .get(plugin_id.to_string()).await
+                .ok_or(ApiError::not_found("Unknown plugin ID"))?;
+            let action = plugin
+                .get_grant(grant_key)
+                .ok_or(ApiError::not_found("Unknown grant key"))?;
+            let config = collections
+                .get::<PluginConfiguration>()
+                .get(config_id.to_string()).await
+                .ok_or(ApiError::not_found("Unknown config ID"))?;
+
+            Ok(UsageGrantContext {
+                plugin,
+                action,
+                config,
+                options,
+                resources,
+            })
+        } else {
+            Err(ApiError::not_found("Unknown plugin grant ID"))
+        }
+    }
+
+    /// Replaces the resource with the same ID in a grant, returning the document path it's stored at.
+    pub fn replace_resource(
+        &mut self,
+        service_id: impl AsRef<str>,
+        grant_id: impl AsRef<str>,
+        resource: GrantResource
+    ) -> Option<String> {
+        let index = self.grant_index(service_id).ok()?;
+        if let GrantResult::Success { value } = &mut self.grants[index].resources {
+            if let Some(GrantResult::Success { value: resources }) = value.get_mut(grant_id.as_ref()) {
+                let position = resources.iter().position(|r| r.id() == resource.id())?;
+                resources[position] = resource;
+                return Some(
+                    format!("grants.{index}.resources.value.{}.value.{position}", grant_id.as_ref())
+                );
+            }
+        }
+        None
+    }
+
+    /// Replaces a resource and writes only that resource back, so changes saved to the rest of the
+    /// usage while a plugin call ran are kept.
+    async fn save_resource(
+        &mut self,
+        service_id: impl AsRef<str>,
+        grant_id: impl AsRef<str>,
+        resource: GrantResource,
+        collections: &Collections
+    ) -> Result<(), ApiError> {
+        let value = bson::to_bson(&resource)
+            .or(Err(ApiError::internal("Failed to save invite usage")))?;
+        if let Some(path) = self.replace_resource(service_id, grant_id, resource) {
+            collections
+                .get::<InviteUsage>()
+                .update_one(doc! { "_id": self.id.to_string() }, doc! { "$set": { path: value } }).await
+                .or(Err(ApiError::internal("Failed to save invite usage")))?;
+        }
+        Ok(())
+    }
+
+    pub async fn revoke(
+        &mut self,
+        service_id: impl AsRef<str>,
+        grant_id: impl AsRef<str>,
+        revoked_by: Id,
+        collections: &Collections,
+        plugins: &PluginRegistry
+    ) -> Result<GrantRevocation, ApiError> {
+        let context = self.resolve_grant(
+            service_id.as_ref(),
+            grant_id.as_ref(),
+            collections,
+            plugins
+        ).await?;
+        let method = context.action.revoke_method
+            .clone()
+            .ok_or(ApiError::method_not_allowed("Grant is not revocable"))?;
+
+        match
+            context.plugin.call_scoped::<_, ()>(
+                method,
+                GrantRevokeParams {
+                    action: context.action.clone(),
+                    plugin_config: context.config.options.into(),
+                    service_config: context.options.into(),
+                    resources: context.resources,
+                },
+                CallScope::config(context.config.id.clone())
+            ).await
+        {
+            Ok(_) => {
+                let revocation = GrantRevocation {
+                    revoked_by,
+                    revoked_at: Utc::now(),
+                };
+                let index = self.grant_index(service_id.as_ref())?;
+                // Only write this grant's revocation, so changes saved while the plugin ran are kept
+                let path = Self::revocation_path(index, grant_id.as_ref());
+                let value = bson::to_bson(&revocation)
+                    .or(Err(ApiError::internal("Failed to save invite usage")))?;
+                collections
+                    .get::<InviteUsage>()
+                    .update_one(doc! { "_id": self.id.to_string() }, doc! { "$set": { path: value } }).await
+                    .or(Err(ApiError::internal("Failed to save invite usage")))?;
+                self.grants[index].revoked.insert(
+                    grant_id.as_ref().to_string(),
+                    revocation.clone()
+                );
+                Ok(revocation)
+            }
+            Err((error, code)) =>
+                Err(ApiError::bad_request(format!("Revocation failed with code {code}: {error:?}"))),
+        }
+    }
+
+    pub async fn admin_actions(
+        &self,
+        collections: &Collections,
+        plugins: &PluginRegistry
+    ) -> Vec<UsageAdminActions> {
+        let mut result: Vec<UsageAdminActions> = Vec::new();
+        for grant in &self.grants {
+            if let GrantResult::Success { value } = &grant.resources {
+                for grant_id in value.keys() {
+                    if
+                        let Ok(context) = self.resolve_grant(
+                            grant.service.to_string(),
+                            grant_id,
+                            collections,
+                            plugins
+                        ).await
+                    {
+                        if context.action.admin_actions.is_empty() {
+                            continue;
+                        }
+
+                        for resource in context.resources {
+                            result.push(UsageAdminActions {
+                                service_id: grant.service.to_string(),
+                                grant_id: grant_id.clone(),
+                                resource,
+                                actions: context.action.admin_actions.clone(),
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn run_admin_action(
+        &mut self,
+        service_id: impl AsRef<str>,
+        grant_id: impl AsRef<str>,
+        resource_id: impl AsRef<str>,
+        action_key: impl AsRef<str>,
+        arguments: HashMap<String, Value>,
+        collections: &Collections,
+        plugins: &PluginRegistry
+    ) -> Result<ActionResult, ApiError> {
+        let context = self.resolve_grant(
+            service_id.as_ref(),
+            grant_id.as_ref(),
+            collections,
+            plugins
+        ).await?;
+        let admin_action = context.action
+            .get_admin_action(action_key)
+            .ok_or(ApiError::not_found("Unknown admin action"))?;
+        let resource = context.resources
+            .iter()
+            .find(|r| r.id() == resource_id.as_ref())
+            .cloned()
+            .ok_or(ApiError::not_found("Unknown resource ID"))?;
+
+        let validated = admin_action.arguments.validate(arguments);
+        if !validated.valid {
+            return Err(
+                ApiError::bad_request(
+                    format!("Invalid arguments: {}", validated.invalid_keys().join(", "))
+                )
+            );
+        }
+
+        match
+            context.plugin.call_scoped::<_, ActionResult>(
+                admin_action.method.clone(),
+                AdminActionParams {
+                    action: admin_action.clone(),
+                    grant: context.action.clone(),
+                    resource,
+                    plugin_config: context.config.options.into(),
+                    service_config: context.options.into(),
+                    arguments: validated.values().into(),
+                },
+                CallScope::config(context.config.id.clone())
+            ).await
+        {
+            Ok(result) => {
+                if let Some(updated) = result.resource.clone() {
+                    self.save_resource(service_id.as_ref(), grant_id.as_ref(), updated, collections).await?;
+                }
+                Ok(result)
+            }
+            Err((error, code)) =>
+                Err(ApiError::bad_request(format!("Action failed with code {code}: {error:?}"))),
+        }
+    }
+
+    pub async fn run_resource_action(
+        &mut self,
+        service_id: impl AsRef<str>,
+        grant_id: impl AsRef<str>,
+        resource_id: impl AsRef<str>,
+        arguments: HashMap<String, Value>,
+        collections: &Collections,
+        plugins: &PluginRegistry
+    ) -> Result<ActionResult, ApiError> {
+        let context = self.resolve_grant(
+            service_id.as_ref(),
+            grant_id.as_ref(),
+            collections,
+            plugins
+        ).await?;
+        let resource = context.resources
+            .iter()
+            .find(|r| r.id() == resource_id.as_ref())
+            .cloned()
+            .ok_or(ApiError::not_found("Unknown resource ID"))?;
+
+        if
+            let GrantResource::Action { method, arguments: action_arguments, metadata, .. } =
+                resource.clone()
+        {
+            let validated = action_arguments.validate(arguments);
+            if !validated.valid {
+                return Err(
+                    ApiError::bad_request(
+                        format!("Invalid arguments: {}", validated.invalid_keys().join(", "))
+                    )
+                );
+            }
+
+            match
+                context.plugin.call_scoped::<_, ActionResult>(
+                    method,
+                    ResourceActionParams {
+                        grant: context.action.clone(),
+                        resource,
+                        metadata,
+                        plugin_config: context.config.options.into(),
+                        service_config: context.options.into(),
+                        arguments: validated.values().into(),
+                    },
+                    CallScope::config(context.config.id.clone())
+                ).await
+            {
+                Ok(result) => {
+                    if let Some(updated) = result.resource.clone() {
+                        self.save_resource(service_id.as_ref(), grant_id.as_ref(), updated, collections).await?;
+                    }
+                    Ok(result)
+                }
+                Err((error, code)) =>
+                    Err(ApiError::bad_request(format!("Action failed with code {code}: {error:?}"))),
+            }
+        } else {
+            Err(ApiError::method_not_allowed("Resource is not an executable action"))
+        }
+    }
+}
+
+/// A file uploaded for a `File` argument while redeeming an invite. Its ID is the stored file's ID,
+/// and redemption only accepts file IDs uploaded for the same invite, grant and field. Each upload
+/// can be redeemed once, and unredeemed uploads expire after [`ArgumentUpload::TTL`].
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect, Document)]
+pub struct ArgumentUpload {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub invite_code: String,
+    pub service: Id,
+    pub grant: String,
+    pub field: String,
+
+    #[reflect(ignore)]
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub uploaded_at: DateTime<Utc>,
+}
+
+impl ArgumentUpload {
+    pub const TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+    /// Checks that every `File` argument with a value refers to a file uploaded for it. When
+    /// `consume` is set, the matching uploads are removed so their IDs can't be redeemed again.
+    pub async fn check_arguments(
+        uploads: &Docs<ArgumentUpload>,
+        invite_code: impl AsRef<str>,
+        service: impl AsRef<str>,
+        grant: impl AsRef<str>,
+        arguments: &[PluginArgument],
+        values: &ParameterMap,
+        consume: bool
+    ) -> Result<(), ApiError> {
+        for argument in arguments.iter().filter(|a| matches!(a.field, FieldType::File { .. })) {
+            let file_id = values.get::<String>(&argument.key).ok().flatten();
+            if let Some(file_id) = file_id {
+                let filter = doc! {
+                    "_id": file_id,
+                    "invite_code": invite_code.as_ref(),
+                    "service": service.as_ref(),
+                    "grant": grant.as_ref(),
+                    "field": argument.key.clone(),
+                };
+                let uploaded = if consume {
+                    matches!(uploads.find_one_and_delete(filter).await, Ok(Some(_)))
+                } else {
+                    uploads.exists(filter).await
+                };
+                if !uploaded {
+                    return Err(
+                        ApiError::bad_request(format!("File for {} was not uploaded with this invite", argument.key))
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    fn invite(expires: Option<Expiration>) -> Invite {
+        Invite {
+            id: Id::default(),
+            code: String::from("code"),
+            created_by: Id::default(),
+            alias: None,
+            expires,
+            services: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn invites_without_expiry_never_expire() {
+        assert!(!invite(None).is_expired(u64::MAX));
+    }
+
+    #[test]
+    fn use_limited_invites_expire_when_used_up() {
+        let invite = invite(Some(Expiration::Uses(2)));
+        assert!(!invite.is_expired(0));
+        assert!(!invite.is_expired(1));
+        assert!(invite.is_expired(2));
+        assert!(invite.is_expired(3));
+    }
+
+    #[test]
+    fn dated_invites_expire_once_the_date_passes() {
+        let future = (Utc::now() + TimeDelta::hours(1)).timestamp_millis();
+        let past = (Utc::now() - TimeDelta::hours(1)).timestamp_millis();
+        assert!(!invite(Some(Expiration::Datetime(future))).is_expired(100));
+        assert!(invite(Some(Expiration::Datetime(past))).is_expired(0));
+    }
+
+    fn account(password: HashedPassword) -> GrantResource {
+        GrantResource::Account {
+            id: String::from("account"),
+            user_id: None,
+            username: None,
+            email: None,
+            password: Some(password),
+            metadata: None,
+        }
+    }
+
+    fn usage(resources: Vec<GrantResource>) -> InviteUsage {
+        InviteUsage {
+            id: Id::default(),
+            user: Id::default(),
+            invite_id: Id::default(),
+            invite_code: String::from("code"),
+            grants: vec![InviteGrant {
+                service: Id::default(),
+                resources: GrantResult::Success {
+                    value: HashMap::from([(String::from("grant"), GrantResult::Success { value: resources })]),
+                },
+                revoked: HashMap::new(),
+                calls: HashMap::new(),
+            }],
+        }
+    }
+
+    /// Follows a dotted update path through the usage as it would be stored.
+    fn stored_at(usage: &InviteUsage, path: &str) -> bson::Bson {
+        let mut stored = bson::to_bson(usage).unwrap();
+        for segment in path.split('.') {
+            stored = match stored {
+                bson::Bson::Document(document) => document.get(segment).cloned().unwrap(),
+                bson::Bson::Array(items) => items[segment.parse::<usize>().unwrap()].clone(),
+                other => panic!("Path runs through a scalar: {other:?}"),
+            };
+        }
+        stored
+    }
+
+    #[test]
+    fn replaced_resources_report_their_stored_path() {
+        let mut usage = usage(vec![account(HashedPassword::new("first")), account(HashedPassword::new("second"))]);
+        let service = usage.grants[0].service.to_string();
+        let replacement = GrantResource::Account {
+            id: String::from("account"),
+            user_id: None,
+            username: Some(String::from("renamed")),
+            email: None,
+            password: None,
+            metadata: None,
+        };
+
+        let path = usage.replace_resource(&service, "grant", replacement.clone()).unwrap();
+        assert_eq!(path, "grants.0.resources.value.grant.value.0");
+        assert_eq!(stored_at(&usage, &path), bson::to_bson(&replacement).unwrap());
+        assert_eq!(usage.replace_resource(&service, "other", replacement), None);
+    }
+
+    #[test]
+    fn password_upgrades_point_at_the_stored_password() {
+        let legacy: HashedPassword = serde_json::from_value(Value::String("ab".repeat(64))).unwrap();
+        let usage = usage(vec![account(HashedPassword::new("current")), account(legacy)]);
+
+        let upgrades = usage.password_upgrades();
+        assert_eq!(upgrades.len(), 1);
+        let (path, old, new) = &upgrades[0];
+        assert_eq!(path, "grants.0.resources.value.grant.value.1.password");
+        assert!(old.is_legacy() && !new.is_legacy());
+
+        assert_eq!(stored_at(&usage, path), bson::to_bson(old).unwrap());
+    }
+
+    #[test]
+    fn revocation_path_points_at_the_stored_revocation() {
+        let mut usage = usage(vec![account(HashedPassword::new("current"))]);
+        let revocation = GrantRevocation { revoked_by: Id::default(), revoked_at: Utc::now() };
+        usage.grants[0].revoked.insert(String::from("grant"), revocation.clone());
+
+        let path = InviteUsage::revocation_path(0, "grant");
+        assert_eq!(stored_at(&usage, &path), bson::to_bson(&revocation).unwrap());
+    }
+}
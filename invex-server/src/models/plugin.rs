@@ -1,14 +1,33 @@
 use anyhow::Error;
 use bevy_reflect::Reflect;
 use bson::doc;
-use extism::{
-    convert::Json, host_fn, Function, Manifest, Plugin as ExtismPlugin, PluginBuilder, UserData, Wasm, PTR
-};
+use chrono::{ DateTime, Utc };
+use extism::{ convert::Json, host_fn, CompiledPlugin, Manifest, PluginBuilder, UserData, Wasm, PTR };
 use invex_macros::Document;
 
 #[allow(unused_imports)]
-use invex_sdk::{ GrantAction, PluginArgument, PluginFileData, PluginMetadata, ExtResult };
-use reqwest::header::HeaderValue;
+use invex_sdk::{
+    call::{
+        HealthCheckResult,
+        LifecycleHook,
+        LimitError,
+        TaskResult,
+        HEALTHCHECK_EXPORT,
+        TEST_CONFIG_EXPORT,
+    },
+    kv::{ KvEntry, KvKey, KvScope },
+    params::{ HealthCheckParams, ScheduledTaskParams },
+    ArgValidator,
+    GrantAction,
+    PluginArgument,
+    PluginFileData,
+    PluginFileMetadata,
+    PluginMetadata,
+    ScheduledTask,
+    ExtResult,
+};
+use regex::{ Captures, Regex };
+use reqwest::{ header::HeaderValue, Url };
 use rocket::{
     futures::{ AsyncWriteExt, TryStreamExt },
     http::Status,
@@ -17,13 +36,101 @@ use rocket::{
 };
 use serde::{ de::DeserializeOwned, Deserialize, Serialize };
 use serde_json::Value;
-use std::{ collections::HashMap, sync::Arc };
-use tokio::{ io::{ AsyncBufRead, AsyncReadExt }, sync::Mutex };
+use semver::Version;
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{ HashMap, HashSet },
+    fmt::Display,
+    future::Future,
+    sync::{ mpsc, Arc },
+    thread,
+    time::Instant,
+};
+use tokio::{ io::{ AsyncBufRead, AsyncReadExt }, runtime::Handle, sync::oneshot };
 
-use crate::util::{ database::{ Docs, File, FileInfo, Fs, Id }, InResult, PluginRegistryMap };
+use crate::{
+    config::{ Config, LogLevel, PluginLimits },
+    util::{
+        database::{ Collections, Docs, File, FileInfo, Fs, Id },
+        pool::{ PluginFactory, PluginPool },
+        signing::{ self, SignatureStatus },
+        validation::{ validate_metadata, ValidationReport },
+        InResult,
+        PluginRegistryMap,
+    },
+};
 
 use super::error::ApiError;
 
+const WASM_PAGE_SIZE: u64 = 65536;
+
+/// Filters an admin's host approvals down to the hosts the plugin actually declares.
+fn approve_hosts(metadata: &PluginMetadata, approved: Vec<String>) -> Vec<String> {
+    approved
+        .into_iter()
+        .filter(|h| metadata.allowed_hosts.contains(h))
+        .collect()
+}
+
+/// Expands `{field}` templates in approved host patterns using every config profile of the plugin.
+/// URL values are reduced to their hostname; patterns referencing a missing field are skipped.
+fn expand_hosts(patterns: &Vec<String>, configs: &Vec<PluginConfiguration>) -> Vec<String> {
+    let template = Regex::new(r"\{([^{}]+)\}").unwrap();
+    let mut hosts: Vec<String> = Vec::new();
+    for pattern in patterns {
+        if !template.is_match(pattern) {
+            if !hosts.contains(pattern) {
+                hosts.push(pattern.clone());
+            }
+            continue;
+        }
+
+        for config in configs {
+            let mut resolved = true;
+            let host = template
+                .replace_all(pattern, |caps: &Captures| {
+                    match config.options.get(&caps[1]).and_then(|v| v.as_str()) {
+                        Some(value) =>
+                            Url::parse(value)
+                                .ok()
+                                .and_then(|u| u.host_str().map(|h| h.to_string()))
+                                .unwrap_or(value.to_string()),
+                        None => {
+                            resolved = false;
+                            String::new()
+                        }
+                    }
+                })
+                .to_string();
+            if resolved && !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+    }
+    hosts
+}
+
+/// Exit code Extism reports for calls that end in a wasm trap, including timeouts.
+const TRAP_EXIT_CODE: i32 = 134;
+
+/// Prefix of the error Extism's HTTP host function raises for responses over
+/// `max_http_response_bytes`.
+const HTTP_RESPONSE_LIMIT_ERROR: &str = "HTTP response exceeds the configured maximum number of bytes";
+
+/// Works out which execution limit stopped a failed call from the error Extism raised for it.
+/// Extism reports both timeouts and cancelled calls as `timeout`, and the HTTP and fuel limits
+/// only through their error messages, so this matches on the error's root cause.
+fn classify_limit_error(error: &Error) -> Option<LimitError> {
+    match error.root_cause().to_string().as_str() {
+        "timeout" => Some(LimitError::Timeout),
+        "oom" => Some(LimitError::Memory),
+        "plugin ran out of fuel" => Some(LimitError::Fuel),
+        cause if cause.starts_with(HTTP_RESPONSE_LIMIT_ERROR) => Some(LimitError::HttpResponse),
+        _ => None,
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Reflect, Document)]
 pub struct RegisteredPlugin {
     #[serde(rename = "_id")]
@@ -34,6 +141,124 @@ pub struct RegisteredPlugin {
     pub source: FileInfo,
     pub url: Option<String>,
     pub enabled: bool,
+
+    #[serde(default)]
+    #[reflect(ignore)]
+    pub limits: Option<PluginLimits>,
+
+    #[serde(default)]
+    pub approved_hosts: Vec<String>,
+
+    #[serde(default)]
+    #[reflect(ignore)]
+    pub versions: Vec<PluginVersion>,
+
+    #[serde(default)]
+    #[reflect(ignore)]
+    pub update: Option<PluginUpdate>,
+
+    #[serde(default)]
+    #[reflect(ignore)]
+    pub signature: SignatureStatus,
+
+    #[serde(default)]
+    #[reflect(ignore)]
+    pub validation: ValidationReport,
+
+    #[serde(default)]
+    #[reflect(ignore)]
+    pub health: Option<PluginHealth>,
+}
+
+impl RegisteredPlugin {
+    /// Saves every field except `health`, which only health checks write. Replacing the whole
+    /// record would roll back a health check that finished while this copy was held.
+    pub async fn save(&self, docs: &Docs<RegisteredPlugin>) -> InResult<()> {
+        docs.update_one(doc! { "_id": self.id.to_string() }, doc! { "$set": self.saved_fields()? })
+            .upsert(true).await?;
+        Ok(())
+    }
+
+    fn saved_fields(&self) -> Result<bson::Document, bson::ser::Error> {
+        let mut fields = bson::to_document(self)?;
+        fields.remove("_id");
+        fields.remove("health");
+        Ok(fields)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    Unhealthy,
+}
+
+/// Outcome of a single `healthcheck` call.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HealthCheck {
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Latest health of a plugin, and of each of its configuration profiles keyed by profile ID.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PluginHealth {
+    pub plugin: HealthCheck,
+
+    #[serde(default)]
+    pub configs: HashMap<String, HealthCheck>,
+}
+
+impl PluginHealth {
+    pub fn config(&self, id: impl AsRef<str>) -> Option<HealthCheck> {
+        self.configs.get(id.as_ref()).cloned()
+    }
+}
+
+/// A newer version found at a plugin's source URL that hasn't been installed yet.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PluginUpdate {
+    pub version: String,
+    pub metadata: PluginMetadata,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// An installed build of a plugin, kept so admins can roll back to it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PluginVersion {
+    pub version: String,
+    pub metadata: PluginMetadata,
+    pub source: FileInfo,
+    pub url: Option<String>,
+    pub installed_at: DateTime<Utc>,
+
+    #[serde(default)]
+    pub signature: SignatureStatus,
+}
+
+impl PluginVersion {
+    fn current(plugin: &RegisteredPlugin) -> Self {
+        PluginVersion {
+            version: plugin.metadata.version.clone(),
+            metadata: plugin.metadata.clone(),
+            source: plugin.source.clone(),
+            url: plugin.url.clone(),
+            installed_at: Utc::now(),
+            signature: plugin.signature.clone(),
+        }
+    }
+}
+
+/// Compares two plugin versions as semver, falling back to treating any different version as newer.
+fn compare_versions(new: &str, current: &str) -> Ordering {
+    match (Version::parse(new), Version::parse(current)) {
+        (Ok(new), Ok(current)) => new.cmp(&current),
+        _ if new == current => Ordering::Equal,
+        _ => Ordering::Greater,
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Reflect, Document)]
@@ -50,12 +275,123 @@ pub struct PluginConfiguration {
     pub options: HashMap<String, Value>,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect, Document)]
+pub struct PluginLog {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub plugin: String,
+    pub method: String,
+    pub call_id: Id,
+
+    #[reflect(ignore)]
+    pub level: LogLevel,
+    pub message: String,
+
+    #[reflect(ignore)]
+    pub timestamp: DateTime<Utc>,
+}
+
+impl PluginLog {
+    /// Most recent log lines matching the filter, newest first.
+    pub async fn recent(
+        docs: &Docs<PluginLog>,
+        filter: bson::Document,
+        limit: i64
+    ) -> InResult<Vec<PluginLog>> {
+        Ok(
+            docs
+                .find(filter)
+                .sort(doc! { "$natural": -1 })
+                .limit(limit).await?
+                .try_collect().await?
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    #[default]
+    Success,
+    Failure,
+}
+
+/// Latest run of a scheduled task. There is one record per plugin, task and configuration profile,
+/// replaced every time the task runs.
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect, Document)]
+pub struct PluginTaskRun {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub plugin: String,
+    pub task: String,
+
+    #[serde(default)]
+    pub config: Option<Id>,
+    pub call_id: Id,
+
+    #[reflect(ignore)]
+    pub status: TaskStatus,
+    pub message: Option<String>,
+
+    #[reflect(ignore)]
+    pub started_at: DateTime<Utc>,
+
+    #[reflect(ignore)]
+    pub finished_at: DateTime<Utc>,
+}
+
+impl PluginTaskRun {
+    /// Filter matching the record for a task under a configuration profile.
+    pub fn filter(plugin: impl AsRef<str>, task: impl AsRef<str>, config: Option<&Id>) -> bson::Document {
+        doc! {
+            "plugin": plugin.as_ref().to_string(),
+            "task": task.as_ref().to_string(),
+            "config": config.map(|c| c.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Reflect, Document)]
+pub struct PluginKv {
+    #[serde(rename = "_id")]
+    pub id: Id,
+    pub plugin: String,
+
+    #[serde(default)]
+    pub config: Option<String>,
+    pub key: String,
+
+    #[reflect(ignore)]
+    pub value: Value,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct PluginInfo {
     pub id: String,
     pub metadata: PluginMetadata,
     pub url: Option<String>,
     pub enabled: bool,
+
+    #[serde(default)]
+    pub limits: Option<PluginLimits>,
+
+    #[serde(default)]
+    pub approved_hosts: Vec<String>,
+
+    #[serde(default)]
+    pub versions: Vec<PluginVersion>,
+
+    #[serde(default)]
+    pub update: Option<PluginUpdate>,
+
+    #[serde(default)]
+    pub signature: SignatureStatus,
+
+    #[serde(default)]
+    pub validation: ValidationReport,
+
+    #[serde(default)]
+    pub health: Option<PluginHealth>,
 }
 
 impl From<Plugin> for PluginInfo {
@@ -65,6 +401,13 @@ impl From<Plugin> for PluginInfo {
             metadata: value.metadata(),
             url: value.url(),
             enabled: value.enabled(),
+            limits: value.limits(),
+            approved_hosts: value.approved_hosts(),
+            versions: value.versions(),
+            update: value.update(),
+            signature: value.signature(),
+            validation: value.validation(),
+            health: value.health(),
         }
     }
 }
@@ -76,6 +419,13 @@ impl From<RegisteredPlugin> for PluginInfo {
             metadata: value.metadata.clone(),
             url: value.url.clone(),
             enabled: value.enabled,
+            limits: value.limits.clone(),
+            approved_hosts: value.approved_hosts.clone(),
+            versions: value.versions.clone(),
+            update: value.update.clone(),
+            signature: value.signature.clone(),
+            validation: value.validation.clone(),
+            health: value.health.clone(),
         }
     }
 }
@@ -85,7 +435,7 @@ impl From<RegisteredPlugin> for PluginInfo {
 pub struct Plugin {
     docs: Docs<RegisteredPlugin>,
     metadata: RegisteredPlugin,
-    plugin: Arc<Mutex<ExtismPlugin>>,
+    pool: Arc<PluginPool>,
 }
 
 impl Plugin {
@@ -109,6 +459,42 @@ impl Plugin {
         self.metadata.enabled = enabled;
     }
 
+    pub fn limits(&self) -> Option<PluginLimits> {
+        self.metadata.limits.clone()
+    }
+
+    pub fn set_limits(&mut self, limits: Option<PluginLimits>) {
+        self.metadata.limits = limits;
+    }
+
+    pub fn approved_hosts(&self) -> Vec<String> {
+        self.metadata.approved_hosts.clone()
+    }
+
+    pub fn set_approved_hosts(&mut self, hosts: Vec<String>) {
+        self.metadata.approved_hosts = approve_hosts(&self.metadata.metadata, hosts);
+    }
+
+    pub fn versions(&self) -> Vec<PluginVersion> {
+        self.metadata.versions.clone()
+    }
+
+    pub fn update(&self) -> Option<PluginUpdate> {
+        self.metadata.update.clone()
+    }
+
+    pub fn signature(&self) -> SignatureStatus {
+        self.metadata.signature.clone()
+    }
+
+    pub fn validation(&self) -> ValidationReport {
+        self.metadata.validation.clone()
+    }
+
+    pub fn health(&self) -> Option<PluginHealth> {
+        self.metadata.health.clone()
+    }
+
     #[allow(dead_code)]
     pub fn info(&self) -> RegisteredPlugin {
         self.metadata.clone()
@@ -119,21 +505,20 @@ impl Plugin {
     }
 
     pub async fn save(&self) -> InResult<()> {
-        self.docs.save(self.metadata.clone()).await?;
-        Ok(())
+        self.metadata.save(&self.docs).await
     }
 
     pub fn get_field(&self, key: impl AsRef<str>) -> Option<PluginArgument> {
         self.metadata()
             .config.iter()
-            .find(|f| f.key == key.as_ref().to_string())
+            .find(|f| f.key == key.as_ref())
             .cloned()
     }
 
     pub fn get_grant(&self, key: impl AsRef<str>) -> Option<GrantAction> {
         self.metadata()
             .grants.iter()
-            .find(|f| f.key == key.as_ref().to_string())
+            .find(|f| f.key == key.as_ref())
             .cloned()
     }
 
@@ -141,37 +526,81 @@ impl Plugin {
         &self,
         method: impl AsRef<str>,
         argument: A
+    ) -> Result<R, (Error, i32)> {
+        self.call_scoped(method, argument, CallScope::default()).await
+    }
+
+    /// Calls a plugin method under an explicit scope. The scope's config profile decides which `Config`
+    /// KV entries the plugin sees, and its ID tags every log line the call produces.
+    pub async fn call_scoped<A: Serialize + DeserializeOwned, R: Serialize + DeserializeOwned>(
+        &self,
+        method: impl AsRef<str>,
+        argument: A,
+        scope: CallScope
     ) -> Result<R, (Error, i32)> {
         let _method = method.as_ref().to_string();
+        let active = ActiveCall {
+            plugin: self.id(),
+            method: _method.clone(),
+            call_id: scope.id,
+            config: scope.config,
+        };
         let _arg = serde_json
             ::to_value(argument)
             .or(Err((Error::msg("Failed to wrap argument"), 500)))?;
-        let plugin = self.plugin.clone();
+        let mut instance = self.pool
+            .checkout().await
+            .map_err(|e| (Error::msg(format!("Failed to acquire a plugin instance: {e}")), 503))?;
         if
             let Ok(result) = tokio::task::spawn_blocking(move || {
-                let mut plugin = plugin.blocking_lock();
+                let plugin = instance
+                    .instance()
+                    .map_err(|e| (Error::msg(format!("Failed to instantiate plugin: {e}")), 500))?;
                 let unwrapped_arg = serde_json
                     ::from_value::<A>(_arg)
                     .or(Err((Error::msg("Failed to unwrap argument"), 500)))?;
+                ACTIVE_CALL.with(|c| {
+                    *c.borrow_mut() = Some(active);
+                });
                 let raw_result = plugin.call_get_error_code::<_, Json<R>>(
                     _method,
                     Json(unwrapped_arg)
                 );
-                match raw_result {
+                ACTIVE_CALL.with(|c| {
+                    *c.borrow_mut() = None;
+                });
+                // A trap or limit error can leave the instance in a broken state. Errors the
+                // plugin returns itself carry its own exit code and are never limit errors.
+                let trapped = matches!(
+                    raw_result,
+                    Err((_, code)) if code <= 0 || code == TRAP_EXIT_CODE
+                );
+                let result = match raw_result {
                     Ok(r) =>
                         serde_json
                             ::to_value(r.into_inner())
-                            .and_then(|v| Ok(v))
                             .or(Err((Error::msg("Failed to wrap raw result"), 500))),
+                    Err((error, code)) if trapped =>
+                        match classify_limit_error(&error) {
+                            Some(limit) =>
+                                Err((
+                                    Error::msg(format!("Plugin exceeded its {limit} limit: {error}")),
+                                    limit as i32,
+                                )),
+                            None => Err((error, code)),
+                        }
                     Err(e) => Err(e),
+                };
+                if trapped {
+                    instance.discard();
                 }
+                result
             }).await
         {
             match result {
                 Ok(r) =>
                     serde_json
                         ::from_value::<R>(r)
-                        .and_then(|v| Ok(v))
                         .or(Err((Error::msg("Failed to unwrap raw result"), 500))),
                 Err(e) => Err(e),
             }
@@ -179,12 +608,138 @@ impl Plugin {
             Err((Error::msg("Failed to execute plugin method in an asynchronous context"), 500))
         }
     }
+
+    /// Checks whether the plugin module exports one of the optional functions, as read when the
+    /// module was loaded.
+    pub fn exports(&self, name: impl AsRef<str>) -> bool {
+        self.pool.exports().contains(name.as_ref())
+    }
+
+    /// Runs the plugin's `healthcheck` once for the plugin itself and once per configuration profile.
+    /// Returns `None` if the plugin doesn't export one.
+    pub async fn check_health(&self, configs: Vec<PluginConfiguration>) -> Option<PluginHealth> {
+        if !self.exports(HEALTHCHECK_EXPORT) {
+            return None;
+        }
+
+        let plugin = self.health_call(HealthCheckParams::default(), CallScope::default()).await;
+        let mut results = HashMap::new();
+        for config in configs {
+            let validation = self.metadata().config.validate(config.options.clone());
+            let check = if validation.valid {
+                self.health_call(
+                    HealthCheckParams {
+                        config_id: Some(config.id.to_string()),
+                        plugin_config: Some(validation.values().into()),
+                    },
+                    CallScope::config(config.id.clone())
+                ).await
+            } else {
+                HealthCheck {
+                    status: HealthStatus::Unhealthy,
+                    latency_ms: 0,
+                    error: Some("Configuration profile is invalid".to_string()),
+                    checked_at: Utc::now(),
+                }
+            };
+            results.insert(config.id.to_string(), check);
+        }
+        Some(PluginHealth { plugin, configs: results })
+    }
+
+    async fn health_call(&self, params: HealthCheckParams, scope: CallScope) -> HealthCheck {
+        let started = Instant::now();
+        let result = self.call_scoped::<_, HealthCheckResult>(HEALTHCHECK_EXPORT, params, scope).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let (status, error) = match result {
+            Ok(r) if r.healthy => (HealthStatus::Healthy, None),
+            Ok(r) =>
+                (
+                    HealthStatus::Unhealthy,
+                    Some(r.message.unwrap_or("Plugin reported itself unhealthy".to_string())),
+                ),
+            Err((e, _)) => (HealthStatus::Unhealthy, Some(e.to_string())),
+        };
+        HealthCheck {
+            status,
+            latency_ms,
+            error,
+            checked_at: Utc::now(),
+        }
+    }
+
+    /// Runs a scheduled task, under a configuration profile if one is given. The returned record
+    /// has a fresh ID; callers replace the previous run's record with it.
+    pub async fn run_task(
+        &self,
+        task: &ScheduledTask,
+        config: Option<PluginConfiguration>
+    ) -> PluginTaskRun {
+        let started_at = Utc::now();
+        let scope = match &config {
+            Some(config) => CallScope::config(config.id.clone()),
+            None => CallScope::default(),
+        };
+        let call_id = scope.id.clone();
+        let plugin_config = match &config {
+            Some(config) => {
+                let validation = self.metadata().config.validate(config.options.clone());
+                if validation.valid { Some(Some(validation.values().into())) } else { None }
+            }
+            None => Some(None),
+        };
+        let (status, message) = match plugin_config {
+            Some(plugin_config) => {
+                let params = ScheduledTaskParams {
+                    task: task.clone(),
+                    config_id: config.as_ref().map(|c| c.id.to_string()),
+                    plugin_config,
+                };
+                match self.call_scoped::<_, TaskResult>(task.method.clone(), params, scope).await {
+                    Ok(result) => (TaskStatus::Success, result.message),
+                    Err((e, _)) => (TaskStatus::Failure, Some(e.to_string())),
+                }
+            }
+            None => (TaskStatus::Failure, Some("Configuration profile is invalid".to_string())),
+        };
+        PluginTaskRun {
+            id: Id::default(),
+            plugin: self.id(),
+            task: task.key.clone(),
+            config: config.map(|c| c.id),
+            call_id,
+            status,
+            message,
+            started_at,
+            finished_at: Utc::now(),
+        }
+    }
+
+    /// Runs a lifecycle hook if the plugin exports it. An error is the plugin's reason for
+    /// vetoing the change.
+    pub async fn run_hook<A: Serialize + DeserializeOwned>(
+        &self,
+        hook: LifecycleHook,
+        argument: A,
+        scope: CallScope
+    ) -> Result<(), String> {
+        if !self.exports(hook.export()) {
+            return Ok(());
+        }
+
+        self.call_scoped::<A, ()>(hook.export(), argument, scope).await
+            .map_err(|(e, _)| format!("{hook} rejected the change: {e}"))
+    }
 }
 
+#[derive(Clone)]
 pub struct PluginRegistry {
     backend: PluginRegistryMap,
     documents: Docs<RegisteredPlugin>,
+    configs: Docs<PluginConfiguration>,
+    kv: Docs<PluginKv>,
     fs: Fs,
+    config: Config,
 }
 
 #[rocket::async_trait]
@@ -192,15 +747,23 @@ impl<'r> FromRequest<'r> for PluginRegistry {
     type Error = ApiError;
 
     async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
-        if let Some(backend) = req.rocket().state::<PluginRegistryMap>() {
-            match req.guard::<Docs<RegisteredPlugin>>().await {
-                request::Outcome::Success(docs) =>
+        if
+            let (Some(backend), Some(config)) = (
+                req.rocket().state::<PluginRegistryMap>(),
+                req.rocket().state::<Config>(),
+            )
+        {
+            match req.guard::<Collections>().await {
+                request::Outcome::Success(collections) =>
                     match req.guard::<Fs>().await {
                         request::Outcome::Success(fs) =>
                             request::Outcome::Success(Self {
                                 backend: backend.clone(),
-                                documents: docs,
+                                documents: collections.get::<RegisteredPlugin>(),
+                                configs: collections.get::<PluginConfiguration>(),
+                                kv: collections.get::<PluginKv>(),
                                 fs: fs.clone(),
+                                config: config.clone(),
                             }),
                         request::Outcome::Error(e) => request::Outcome::Error(e),
                         request::Outcome::Forward(_) => panic!("Unreachable!"),
@@ -217,86 +780,467 @@ impl<'r> FromRequest<'r> for PluginRegistry {
     }
 }
 
-host_fn!(fs_load(user_data: Fs; id: String) -> Json<PluginFileData> {
-    tokio::runtime::Builder::new_multi_thread().enable_all().build()?.block_on(async move {
-        let fs = user_data.get()?;
-        let fs = fs.lock().unwrap();
-        if let Some(file) = fs.get_file(id.into()).await {
-            Ok(Json(PluginFileData {
-                data: file.read().await.unwrap(),
-                filename: file.original_filename.clone(),
-                content_type: file.content_type.clone()
-            }))
-        } else {
-            Err(anyhow::Error::msg("Unknown file ID"))
+/// Identifies a plugin call, and optionally the config profile it is made on behalf of.
+#[derive(Clone, Debug, Default)]
+pub struct CallScope {
+    pub id: Id,
+    pub config: Option<Id>,
+}
+
+impl CallScope {
+    pub fn config(config: Id) -> Self {
+        CallScope {
+            id: Id::default(),
+            config: Some(config),
         }
-    })
+    }
+}
+
+/// The plugin call currently running on a thread.
+#[derive(Clone, Debug)]
+pub struct ActiveCall {
+    pub plugin: String,
+    pub method: String,
+    pub call_id: Id,
+    pub config: Option<Id>,
+}
+
+thread_local! {
+    /// Host functions and the Extism log callback run synchronously on the thread that called into the
+    /// plugin, so this is how they find out which call (and config profile) they belong to.
+    static ACTIVE_CALL: RefCell<Option<ActiveCall>> = const { RefCell::new(None) };
+}
+
+pub fn active_call() -> Option<ActiveCall> {
+    ACTIVE_CALL.with(|c| c.borrow().clone())
+}
+
+/// State shared by every host function of a plugin instance. Plugin calls run on blocking threads, so
+/// async work is driven on the Rocket runtime through `handle` instead of a runtime of its own.
+#[derive(Clone)]
+pub struct HostContext {
+    fs: Fs,
+    kv: Docs<PluginKv>,
+    plugin: Option<String>,
+    handle: Handle,
+}
+
+/// Exports a plugin may leave out, which the server checks for before calling.
+fn optional_exports() -> Vec<&'static str> {
+    let mut exports = vec![HEALTHCHECK_EXPORT, TEST_CONFIG_EXPORT];
+    exports.extend(LifecycleHook::ALL.iter().map(LifecycleHook::export));
+    exports
+}
+
+/// Channel a pool's module thread answers an instantiation request on.
+type InstanceReply = mpsc::Sender<InResult<extism::Plugin>>;
+
+/// Compiles a plugin module with the given limits, allowed hosts and host functions.
+fn compile_module(
+    data: Vec<u8>,
+    limits: PluginLimits,
+    hosts: Vec<String>,
+    host: HostContext
+) -> InResult<CompiledPlugin> {
+    let mut manifest = Manifest::new([Wasm::data(data)]).with_allowed_hosts(hosts.into_iter());
+    if let Some(timeout) = limits.timeout {
+        manifest = manifest.with_timeout(timeout.into());
+    }
+    if let Some(max_memory) = limits.max_memory {
+        manifest = manifest.with_memory_max((max_memory / WASM_PAGE_SIZE).max(1) as u32);
+    }
+    if let Some(max_http_response) = limits.max_http_response {
+        manifest.memory.max_http_response_bytes = Some(max_http_response);
+    }
+
+    let context = UserData::new(host);
+    let mut builder = PluginBuilder::new(manifest)
+        .with_wasi(true)
+        .with_function("fs_load", [PTR], [PTR], context.clone(), fs_load)
+        .with_function("fs_store", [PTR], [PTR], context.clone(), fs_store)
+        .with_function("fs_info", [PTR], [PTR], context.clone(), fs_info)
+        .with_function("fs_load_raw", [PTR], [PTR], context.clone(), fs_load_raw)
+        .with_function("fs_store_raw", [PTR, PTR], [PTR], context.clone(), fs_store_raw)
+        .with_function("kv_get", [PTR], [PTR], context.clone(), kv_get)
+        .with_function("kv_set", [PTR], [], context.clone(), kv_set)
+        .with_function("kv_delete", [PTR], [PTR], context.clone(), kv_delete)
+        .with_function("kv_list", [PTR], [PTR], context.clone(), kv_list)
+        .with_function("random_bytes", [PTR], [PTR], context.clone(), random_bytes);
+    if let Some(fuel) = limits.fuel {
+        builder = builder.with_fuel_limit(fuel);
+    }
+    Ok(builder.compile()?)
+}
+
+impl HostContext {
+    fn from_user_data(user_data: &UserData<HostContext>) -> Result<Self, Error> {
+        let context = user_data.get()?;
+        let context = context
+            .lock()
+            .or(Err(Error::msg("Host function context is poisoned")))?;
+        Ok(context.clone())
+    }
+
+    fn run<T, E: Display>(&self, future: impl Future<Output = Result<T, E>>) -> Result<T, Error> {
+        self.handle.block_on(future).map_err(|e| Error::msg(e.to_string()))
+    }
+
+    fn namespace(&self, scope: KvScope) -> Result<(String, Option<String>), Error> {
+        let plugin = self.plugin
+            .clone()
+            .ok_or(Error::msg("The KV store is unavailable while loading plugin metadata"))?;
+        let config = match scope {
+            KvScope::Plugin => None,
+            KvScope::Config =>
+                Some(
+                    active_call()
+                        .and_then(|call| call.config)
+                        .map(|id| id.to_string())
+                        .ok_or(Error::msg("No configuration profile is active for this call"))?
+                ),
+        };
+        Ok((plugin, config))
+    }
+
+    fn filter(&self, scope: KvScope) -> Result<bson::Document, Error> {
+        let (plugin, config) = self.namespace(scope)?;
+        Ok(doc! { "plugin": plugin, "config": config })
+    }
+
+    async fn load_file(&self, id: String) -> InResult<(File, Vec<u8>)> {
+        let file = self.fs.get_file(id.into()).await.ok_or(Error::msg("Unknown file ID"))?;
+        let data = file.read().await?;
+        Ok((file, data))
+    }
+
+    async fn store_file(
+        &self,
+        metadata: PluginFileMetadata,
+        data: &[u8]
+    ) -> InResult<String> {
+        let (file, mut uploader) = self.fs.get_uploader(
+            metadata.content_type,
+            metadata.filename
+        ).await?;
+        uploader.write_all(data).await?;
+        uploader.close().await?;
+        Ok(file.id.to_string())
+    }
+}
+
+host_fn!(fs_load(user_data: HostContext; id: String) -> Json<PluginFileData> {
+    let context = HostContext::from_user_data(&user_data)?;
+    let (file, data) = context.run(context.load_file(id))?;
+    Ok(Json(PluginFileData {
+        data,
+        filename: file.original_filename,
+        content_type: file.content_type
+    }))
+});
+
+host_fn!(fs_store(user_data: HostContext; data: Json<PluginFileData>) -> String {
+    let context = HostContext::from_user_data(&user_data)?;
+    let file = data.into_inner();
+    context.run(context.store_file(
+        PluginFileMetadata { filename: file.filename, content_type: file.content_type },
+        &file.data
+    ))
 });
 
-host_fn!(fs_store(user_data: Fs; data: Json<PluginFileData>) -> String {
-    tokio::runtime::Builder::new_multi_thread().enable_all().build()?.block_on(async move {
-        let fs = user_data.get()?;
-        let fs = fs.lock().unwrap();
-        let file = data.into_inner();
-        let (file_instance, mut uploader) = fs.get_uploader(file.content_type.clone(), file.filename.clone()).await.unwrap();
-        uploader.write_all(file.data.as_slice()).await.unwrap();
-        uploader.flush().await.unwrap();
-        uploader.close().await.unwrap();
-        Ok(file_instance.id.to_string())
-    })
+host_fn!(fs_info(user_data: HostContext; id: String) -> Json<PluginFileMetadata> {
+    let context = HostContext::from_user_data(&user_data)?;
+    let file = context.handle
+        .block_on(context.fs.get_file(id.into()))
+        .ok_or(Error::msg("Unknown file ID"))?;
+    Ok(Json(PluginFileMetadata {
+        filename: file.original_filename,
+        content_type: file.content_type
+    }))
+});
+
+host_fn!(fs_load_raw(user_data: HostContext; id: String) -> Vec<u8> {
+    let context = HostContext::from_user_data(&user_data)?;
+    let (_, data) = context.run(context.load_file(id))?;
+    Ok(data)
+});
+
+host_fn!(fs_store_raw(user_data: HostContext; metadata: Json<PluginFileMetadata>, data: Vec<u8>) -> String {
+    let context = HostContext::from_user_data(&user_data)?;
+    context.run(context.store_file(metadata.into_inner(), &data))
+});
+
+host_fn!(kv_get(user_data: HostContext; key: Json<KvKey>) -> Json<Option<Value>> {
+    let context = HostContext::from_user_data(&user_data)?;
+    let key = key.into_inner();
+    let mut filter = context.filter(key.scope)?;
+    filter.insert("key", key.key);
+    let entry = context.run(async { context.kv.find_one(filter).await })?;
+    Ok(Json(entry.map(|e| e.value)))
+});
+
+host_fn!(kv_set(user_data: HostContext; entry: Json<KvEntry>) {
+    let context = HostContext::from_user_data(&user_data)?;
+    let entry = entry.into_inner();
+    let (plugin, config) = context.namespace(entry.scope)?;
+    let value = bson::to_bson(&entry.value)?;
+    let id = bson::to_bson(&Id::default())?;
+    context.run(async {
+        context.kv
+            .update_one(
+                doc! { "plugin": plugin, "config": config, "key": entry.key },
+                doc! { "$set": { "value": value }, "$setOnInsert": { "_id": id } }
+            )
+            .upsert(true).await
+    })?;
+    Ok(())
+});
+
+host_fn!(kv_delete(user_data: HostContext; key: Json<KvKey>) -> Json<bool> {
+    let context = HostContext::from_user_data(&user_data)?;
+    let key = key.into_inner();
+    let mut filter = context.filter(key.scope)?;
+    filter.insert("key", key.key);
+    let result = context.run(async { context.kv.delete_one(filter).await })?;
+    Ok(Json(result.deleted_count > 0))
+});
+
+host_fn!(kv_list(user_data: HostContext; prefix: Json<KvKey>) -> Json<Vec<KvEntry>> {
+    let context = HostContext::from_user_data(&user_data)?;
+    let prefix = prefix.into_inner();
+    let mut filter = context.filter(prefix.scope)?;
+    filter.insert("key", doc! { "$regex": format!("^{}", regex::escape(&prefix.key)) });
+    let entries = context.run(context.kv.query_many(filter))?;
+    Ok(
+        Json(
+            entries
+                .into_iter()
+                .map(|e| KvEntry { key: e.key, value: e.value, scope: prefix.scope })
+                .collect::<Vec<_>>()
+        )
+    )
+});
+
+/// Largest number of random bytes a plugin can request at once.
+const MAX_RANDOM_BYTES: u64 = 64 * 1024;
+
+host_fn!(random_bytes(_user_data: HostContext; length: u64) -> Vec<u8> {
+    if length > MAX_RANDOM_BYTES {
+        return Err(Error::msg(format!("Can't request more than {MAX_RANDOM_BYTES} random bytes at once")));
+    }
+    let mut buffer = vec![0u8; length as usize];
+    if length > 0 {
+        orion::util::secure_rand_bytes(&mut buffer).or(Err(Error::msg("Failed to generate random bytes")))?;
+    }
+    Ok(buffer)
 });
 
 impl PluginRegistry {
-    pub fn new(registry: PluginRegistryMap, documents: Docs<RegisteredPlugin>, fs: Fs) -> Self {
+    pub fn new(
+        registry: PluginRegistryMap,
+        documents: Docs<RegisteredPlugin>,
+        configs: Docs<PluginConfiguration>,
+        kv: Docs<PluginKv>,
+        fs: Fs,
+        config: Config
+    ) -> Self {
         PluginRegistry {
             backend: registry,
             documents,
+            configs,
+            kv,
             fs,
+            config,
         }
     }
 
-    async fn resolve(&self, file: File) -> InResult<(ExtismPlugin, PluginMetadata)> {
-        let temp = file.clone().read_to_temp().await?;
-        let wasm = Wasm::file(temp.path());
-        let manifest = Manifest::new([wasm]).with_allowed_host("*");
-        let mut plugin = PluginBuilder::new(manifest)
-            .with_wasi(true)
-            .with_function("fs_load", [PTR], [PTR], UserData::new(self.fs.clone()), fs_load)
-            .with_function("fs_store", [PTR], [PTR], UserData::new(self.fs.clone()), fs_store)
-            .build()?;
-        let metadata = plugin.call::<(), Json<PluginMetadata>>("metadata", ())?.into_inner();
-        Ok((plugin, metadata))
+    /// Compiles the plugin module once with the given limits and allowed hosts and returns a
+    /// factory that instantiates it, along with which of the optional exports the module provides.
+    /// Extism's `CompiledPlugin` isn't `Send`, so a dedicated thread compiles and owns the module
+    /// and builds instances on request; the thread exits once the factory is dropped.
+    async fn factory(
+        &self,
+        data: Vec<u8>,
+        limits: PluginLimits,
+        hosts: Vec<String>,
+        plugin: Option<String>
+    ) -> InResult<(PluginFactory, HashSet<String>)> {
+        let host = HostContext {
+            fs: self.fs.clone(),
+            kv: self.kv.clone(),
+            plugin: plugin.clone(),
+            handle: Handle::current(),
+        };
+        let (ready, compiled) = oneshot::channel::<InResult<(PluginFactory, HashSet<String>)>>();
+        thread::Builder
+            ::new()
+            .name(format!("plugin-module-{}", plugin.as_deref().unwrap_or("metadata")))
+            .spawn(move || {
+                let module = match compile_module(data, limits, hosts, host) {
+                    Ok(module) => module,
+                    Err(e) => {
+                        let _ = ready.send(Err(e));
+                        return;
+                    }
+                };
+                let exports = match extism::Plugin::new_from_compiled(&module) {
+                    Ok(probe) =>
+                        optional_exports()
+                            .into_iter()
+                            .filter(|name| probe.function_exists(name))
+                            .map(String::from)
+                            .collect(),
+                    Err(e) => {
+                        let _ = ready.send(Err(e.into()));
+                        return;
+                    }
+                };
+
+                let (requests, incoming) = mpsc::channel::<InstanceReply>();
+                let factory: PluginFactory = Arc::new(move || {
+                    let (reply, instance) = mpsc::channel();
+                    requests.send(reply).or(Err("The plugin's module thread has stopped"))?;
+                    instance.recv().or(Err("The plugin's module thread has stopped"))?
+                });
+                if ready.send(Ok((factory, exports))).is_err() {
+                    return;
+                }
+                for reply in incoming {
+                    let _ = reply.send(extism::Plugin::new_from_compiled(&module).map_err(Into::into));
+                }
+            })?;
+        compiled.await?
+    }
+
+    async fn pool(&self, plugin: &RegisteredPlugin, data: Vec<u8>) -> InResult<Arc<PluginPool>> {
+        let conf = &self.config.plugin_pool;
+        let limits = self.config.plugin_limits.merge(plugin.limits.clone());
+        let configs = self.configs.query_many(
+            doc! { "plugin": plugin.metadata.id.clone() }
+        ).await?;
+        let hosts = expand_hosts(&plugin.approved_hosts, &configs);
+        let (factory, exports) = self.factory(
+            data,
+            limits,
+            hosts,
+            Some(plugin.metadata.id.clone())
+        ).await?;
+        Ok(Arc::new(
+            PluginPool::new(
+                factory,
+                exports,
+                conf.max_instances_for(plugin.metadata.id.clone()),
+                conf.max_idle,
+                conf.checkout_timeout.map(|t| t.into())
+            )
+        ))
+    }
+
+    /// Loads a throwaway instance of the plugin and reads its metadata. Runs on a blocking thread
+    /// like any other plugin call, so host functions can drive the runtime. The metadata is
+    /// checked against the module's exports while the instance is still around.
+    async fn read_metadata(&self, data: Vec<u8>) -> InResult<(PluginMetadata, ValidationReport)> {
+        let (factory, _) = self.factory(data, self.config.plugin_limits.merge(None), Vec::new(), None).await?;
+        tokio::task::spawn_blocking(move || -> InResult<(PluginMetadata, ValidationReport)> {
+            let mut plugin = factory()?;
+            let metadata = plugin.call::<(), Json<PluginMetadata>>("metadata", ())?.into_inner();
+            let report = validate_metadata(&metadata, |name| plugin.function_exists(name));
+            Ok((metadata, report))
+        }).await?
+    }
+
+    async fn resolve(&self, file: File) -> InResult<(Vec<u8>, PluginMetadata, ValidationReport)> {
+        let data = file.read().await?;
+        let (metadata, validation) = self.read_metadata(data.clone()).await?;
+        Ok((data, metadata, validation))
     }
 
     async fn store(
         &self,
         metadata: PluginMetadata,
         source: FileInfo,
-        url: Option<String>
+        url: Option<String>,
+        approved_hosts: Option<Vec<String>>,
+        signature: SignatureStatus,
+        validation: ValidationReport
     ) -> InResult<RegisteredPlugin> {
         if
             let Some(existing) = self.documents.query_one(
                 doc! { "metadata.id": metadata.id.clone() }
             ).await
         {
-            let plugin = RegisteredPlugin {
+            let mut versions = existing.versions.clone();
+            if versions.is_empty() {
+                versions.push(PluginVersion::current(&existing));
+            }
+
+            if source.id != existing.source.id {
+                match compare_versions(&metadata.version, &existing.metadata.version) {
+                    Ordering::Less => {
+                        let _ = self.fs.delete(source.id.clone()).await;
+                        return Err(
+                            format!(
+                                "Version {} is older than the installed version {}, roll back to it instead",
+                                metadata.version,
+                                existing.metadata.version
+                            ).into()
+                        );
+                    }
+                    Ordering::Equal => {
+                        // Re-uploading the same version replaces that build
+                        versions.retain(|v| v.version != metadata.version);
+                        let _ = self.fs.delete(existing.source.id.clone()).await;
+                    }
+                    Ordering::Greater => (),
+                }
+            }
+
+            let approved_hosts = approve_hosts(
+                &metadata,
+                approved_hosts.unwrap_or(existing.approved_hosts.clone())
+            );
+            let health = existing.health.clone();
+            let update = existing.update.filter(
+                |u| compare_versions(&u.version, &metadata.version) == Ordering::Greater
+            );
+            let mut plugin = RegisteredPlugin {
                 id: existing.id,
                 metadata,
                 source,
                 url,
                 enabled: existing.enabled,
+                limits: existing.limits,
+                approved_hosts,
+                versions,
+                update,
+                signature,
+                validation,
+                health,
             };
-            self.documents.save(plugin.clone()).await?;
+            let current = PluginVersion::current(&plugin);
+            if let Some(known) = plugin.versions.iter_mut().find(|v| v.source.id == current.source.id) {
+                known.metadata = current.metadata;
+            } else {
+                plugin.versions.push(current);
+            }
+            plugin.save(&self.documents).await?;
             Ok(plugin)
         } else {
-            let plugin = RegisteredPlugin {
+            let mut plugin = RegisteredPlugin {
                 id: Id::default(),
                 metadata: metadata.clone(),
                 source,
                 url,
-                enabled: metadata.config.len() == 0,
+                enabled: metadata.config.is_empty(),
+                limits: None,
+                approved_hosts: approve_hosts(&metadata, approved_hosts.unwrap_or_default()),
+                versions: Vec::new(),
+                update: None,
+                signature,
+                validation,
+                health: None,
             };
-            self.documents.save(plugin.clone()).await?;
+            plugin.versions.push(PluginVersion::current(&plugin));
+            plugin.save(&self.documents).await?;
             Ok(plugin)
         }
     }
@@ -304,35 +1248,103 @@ impl PluginRegistry {
     pub async fn register_existing(&self, plugin: RegisteredPlugin) -> InResult<Plugin> {
         let mut registry = self.backend.lock().await;
         let file = File::from_info(plugin.source.clone(), &self.fs);
-        let (plugin, metadata) = self.resolve(file.clone()).await?;
-        let plugin_info = self.store(metadata, file.into(), None).await?;
-        let plugin_arc = Arc::new(Mutex::new(plugin));
-        registry.insert(plugin_info.metadata.id.clone(), plugin_arc.clone());
+        let (data, metadata, validation) = self.resolve(file.clone()).await?;
+        let plugin_info = self.store(
+            metadata,
+            file.into(),
+            plugin.url.clone(),
+            None,
+            plugin.signature.clone(),
+            validation
+        ).await?;
+        let pool = self.pool(&plugin_info, data).await?;
+        registry.insert(plugin_info.metadata.id.clone(), pool.clone());
         Ok(Plugin {
             metadata: plugin_info,
-            plugin: plugin_arc.clone(),
+            pool,
             docs: self.documents.clone(),
         })
     }
 
-    pub async fn register_file(&self, file: File) -> InResult<Plugin> {
+    /// Checks a plugin's signature against the trusted publisher keys, and only then loads it to read
+    /// its metadata, so untrusted modules are never instantiated.
+    async fn inspect(
+        &self,
+        data: Vec<u8>,
+        signature: Option<Vec<u8>>
+    ) -> InResult<(PluginMetadata, ValidationReport, SignatureStatus)> {
+        let status = signing::verify(&data, signature.as_deref(), &self.config.plugin_signing)?;
+        let (metadata, validation) = self.read_metadata(data).await?;
+        Ok((metadata, validation, status))
+    }
+
+    /// Like [`Self::inspect`], but also rejects plugins whose metadata is invalid.
+    async fn inspect_for_install(
+        &self,
+        data: Vec<u8>,
+        signature: Option<Vec<u8>>
+    ) -> InResult<(PluginMetadata, ValidationReport, SignatureStatus)> {
+        let (metadata, validation, status) = self.inspect(data, signature).await?;
+        if !validation.is_valid() {
+            return Err(format!("Plugin metadata is invalid: {}", validation.summary()).into());
+        }
+        Ok((metadata, validation, status))
+    }
+
+    /// Fetches the detached signature published next to a plugin URL. A missing `.sig` means the
+    /// plugin is unsigned, but any other failure is an error rather than a reason to skip the check.
+    async fn fetch_signature(&self, url: &str) -> InResult<Option<Vec<u8>>> {
+        let res = reqwest::get(format!("{url}.sig")).await?;
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            Ok(None)
+        } else {
+            Ok(Some(res.error_for_status()?.bytes().await?.to_vec()))
+        }
+    }
+
+    pub async fn register_file(
+        &self,
+        file: File,
+        signature: Option<Vec<u8>>,
+        approved_hosts: Option<Vec<String>>
+    ) -> InResult<Plugin> {
         let mut registry = self.backend.lock().await;
-        let (plugin, metadata) = self.resolve(file.clone()).await?;
-        let plugin_info = self.store(metadata, file.into(), None).await?;
-        let plugin_arc = Arc::new(Mutex::new(plugin));
-        registry.insert(plugin_info.metadata.id.clone(), plugin_arc.clone());
+        let data = file.read().await?;
+        let (metadata, validation, status) = match self.inspect_for_install(data.clone(), signature).await {
+            Ok(inspected) => inspected,
+            Err(e) => {
+                let _ = self.fs.delete(file.id.clone()).await;
+                return Err(e);
+            }
+        };
+        let plugin_info = self.store(
+            metadata,
+            file.into(),
+            None,
+            approved_hosts,
+            status,
+            validation
+        ).await?;
+        let pool = self.pool(&plugin_info, data).await?;
+        registry.insert(plugin_info.metadata.id.clone(), pool.clone());
         Ok(Plugin {
             metadata: plugin_info,
-            plugin: plugin_arc.clone(),
+            pool,
             docs: self.documents.clone(),
         })
     }
 
-    pub async fn register_url(&self, url: String) -> InResult<Plugin> {
+    pub async fn register_url(
+        &self,
+        url: String,
+        approved_hosts: Option<Vec<String>>
+    ) -> InResult<Plugin> {
         let mut registry = self.backend.lock().await;
         let req = reqwest::get(url.clone()).await?.error_for_status()?;
         let headers = req.headers().clone();
         let content = req.bytes().await?;
+        let signature = self.fetch_signature(&url).await?;
+        let (metadata, validation, status) = self.inspect_for_install(content.to_vec(), signature).await?;
         let (file, mut uploader) = self.fs.get_uploader(
             headers
                 .get("Content-Type")
@@ -345,53 +1357,176 @@ impl PluginRegistry {
             uploader.write_all(chunk).await?;
         }
         uploader.close().await?;
-        let (plugin, metadata) = self.resolve(file.clone()).await?;
-        let plugin_info = self.store(metadata, file.into(), Some(url)).await?;
-        let plugin_arc = Arc::new(Mutex::new(plugin));
-        registry.insert(plugin_info.metadata.id.clone(), plugin_arc.clone());
+        let plugin_info = self.store(
+            metadata,
+            file.into(),
+            Some(url),
+            approved_hosts,
+            status,
+            validation
+        ).await?;
+        let pool = self.pool(&plugin_info, content.to_vec()).await?;
+        registry.insert(plugin_info.metadata.id.clone(), pool.clone());
         Ok(Plugin {
             metadata: plugin_info,
-            plugin: plugin_arc.clone(),
+            pool,
             docs: self.documents.clone(),
         })
     }
 
+    /// Rebuilds the instance pool for a registered plugin from its stored record, discarding
+    /// any idle instances built with outdated settings.
+    pub async fn reload<T: AsRef<str>>(&self, id: T) -> InResult<Plugin> {
+        let mut registry = self.backend.lock().await;
+        let info = self.documents
+            .query_one(doc! { "metadata.id": id.as_ref().to_string() }).await
+            .ok_or("Unknown plugin ID")?;
+        let data = self.fs.download(info.source.id.clone()).await?;
+        let pool = self.pool(&info, data).await?;
+        registry.insert(info.metadata.id.clone(), pool.clone());
+        Ok(Plugin {
+            metadata: info,
+            pool,
+            docs: self.documents.clone(),
+        })
+    }
+
+    /// Fetches a URL plugin's source again and records whether it offers a newer version.
+    pub async fn check_update<T: AsRef<str>>(&self, id: T) -> InResult<Option<PluginUpdate>> {
+        let mut info = self.documents
+            .query_one(doc! { "metadata.id": id.as_ref().to_string() }).await
+            .ok_or("Unknown plugin ID")?;
+        let url = info.url.clone().ok_or("Plugin was not installed from a URL")?;
+        let preview = self.preview_url(url).await?;
+        if preview.id != info.metadata.id {
+            return Err(format!("Source URL now serves a different plugin ({})", preview.id).into());
+        }
+
+        info.update = match
+            compare_versions(&preview.metadata.version, &info.metadata.version)
+        {
+            Ordering::Greater =>
+                Some(PluginUpdate {
+                    version: preview.metadata.version.clone(),
+                    metadata: preview.metadata,
+                    checked_at: Utc::now(),
+                }),
+            _ => None,
+        };
+        info.save(&self.documents).await?;
+        Ok(info.update)
+    }
+
+    /// Checks every URL plugin for updates, returning the plugins that have one available.
+    pub async fn check_updates(&self) -> InResult<Vec<PluginInfo>> {
+        let mut updated = Vec::new();
+        for plugin in self.documents.query_many(doc! { "url": { "$ne": null } }).await? {
+            match self.check_update(plugin.metadata.id.clone()).await {
+                Ok(Some(_)) => {
+                    if let Some(info) = self.get(plugin.metadata.id.clone()).await {
+                        updated.push(info.into());
+                    }
+                }
+                Ok(None) => (),
+                Err(e) => println!("Failed to check {} for updates: {e}", plugin.metadata.id),
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Health-checks a plugin and its configuration profiles, storing the result on its record.
+    pub async fn check_health<T: AsRef<str>>(&self, id: T) -> InResult<Option<PluginHealth>> {
+        let plugin = self.get(id.as_ref()).await.ok_or("Unknown plugin ID")?;
+        let configs = self.configs.query_many(doc! { "plugin": plugin.id() }).await?;
+        let health = plugin.check_health(configs).await;
+        self.documents.update_one(
+            doc! { "metadata.id": plugin.id() },
+            doc! { "$set": { "health": bson::to_bson(&health)? } }
+        ).await?;
+        Ok(health)
+    }
+
+    /// Health-checks every enabled plugin.
+    pub async fn check_all_health(&self) -> InResult<()> {
+        for plugin in self.documents.query_many(doc! { "enabled": true }).await? {
+            if let Err(e) = self.check_health(plugin.metadata.id.clone()).await {
+                println!("Failed to check health of {}: {e}", plugin.metadata.id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Installs the newer version found by the last update check.
+    pub async fn apply_update<T: AsRef<str>>(&self, id: T) -> InResult<Plugin> {
+        let info = self.documents
+            .query_one(doc! { "metadata.id": id.as_ref().to_string() }).await
+            .ok_or("Unknown plugin ID")?;
+        if info.update.is_none() {
+            return Err("No update is available for this plugin".into());
+        }
+        let url = info.url.clone().ok_or("Plugin was not installed from a URL")?;
+        self.register_url(url, None).await
+    }
+
+    /// Switches a plugin back to one of its previously installed versions.
+    pub async fn rollback<T: AsRef<str>>(&self, id: T, version: impl AsRef<str>) -> InResult<Plugin> {
+        let mut info = self.documents
+            .query_one(doc! { "metadata.id": id.as_ref().to_string() }).await
+            .ok_or("Unknown plugin ID")?;
+        let target = info.versions
+            .iter()
+            .find(|v| v.version == version.as_ref())
+            .cloned()
+            .ok_or("Unknown plugin version")?;
+        info.metadata = target.metadata;
+        info.source = target.source;
+        info.url = target.url;
+        info.signature = target.signature;
+        info.approved_hosts = approve_hosts(&info.metadata, info.approved_hosts.clone());
+        info.save(&self.documents).await?;
+        self.reload(id).await
+    }
+
     pub async fn preview_file(
         &self,
-        mut buffer: impl AsyncBufRead + Unpin
+        mut buffer: impl AsyncBufRead + Unpin,
+        signature: Option<Vec<u8>>
     ) -> InResult<PluginInfo> {
         let mut data = Vec::<u8>::new();
         buffer.read_to_end(&mut data).await?;
-        let wasm = Wasm::data(data);
-        let manifest = Manifest::new([wasm]);
-        let mut plugin = ExtismPlugin::new(&manifest, [
-            Function::new("fs_store", [PTR], [PTR], UserData::new(self.fs.clone()), fs_store),
-            Function::new("fs_load", [PTR], [PTR], UserData::new(self.fs.clone()), fs_load)
-        ], true)?;
-        let metadata = plugin.call::<(), Json<PluginMetadata>>("metadata", ())?.into_inner();
+        let (metadata, validation, signature) = self.inspect(data, signature).await?;
         Ok(PluginInfo {
             id: metadata.id.clone(),
             metadata: metadata.clone(),
             url: None,
             enabled: false,
+            limits: None,
+            approved_hosts: Vec::new(),
+            versions: Vec::new(),
+            update: None,
+            signature,
+            validation,
+            health: None,
         })
     }
 
     pub async fn preview_url(&self, url: String) -> InResult<PluginInfo> {
         let req = reqwest::get(url.clone()).await?.error_for_status()?;
         let content = req.bytes().await?;
-        let wasm = Wasm::data(content);
-        let manifest = Manifest::new([wasm]);
-        let mut plugin = ExtismPlugin::new(&manifest, [
-            Function::new("fs_store", [PTR], [PTR], UserData::new(self.fs.clone()), fs_store),
-            Function::new("fs_load", [PTR], [PTR], UserData::new(self.fs.clone()), fs_load)
-        ], true)?;
-        let metadata = plugin.call::<(), Json<PluginMetadata>>("metadata", ())?.into_inner();
+        let signature = self.fetch_signature(&url).await?;
+        let (metadata, validation, signature) = self.inspect(content.to_vec(), signature).await?;
         Ok(PluginInfo {
             id: metadata.id.clone(),
             metadata: metadata.clone(),
             url: None,
             enabled: false,
+            limits: None,
+            approved_hosts: Vec::new(),
+            versions: Vec::new(),
+            update: None,
+            signature,
+            validation,
+            health: None,
         })
     }
 
@@ -402,15 +1537,11 @@ impl PluginRegistry {
                 doc! { "metadata.id": id.as_ref().to_string() }
             ).await
         {
-            if let Some(instance) = registry.get(id.as_ref()) {
-                Some(Plugin {
-                    metadata: info,
-                    plugin: instance.clone(),
-                    docs: self.documents.clone(),
-                })
-            } else {
-                None
-            }
+            registry.get(id.as_ref()).map(|pool| Plugin {
+                metadata: info,
+                pool: pool.clone(),
+                docs: self.documents.clone(),
+            })
         } else {
             None
         }
@@ -427,15 +1558,23 @@ impl PluginRegistry {
         )
     }
 
-    pub async fn deregister<T: AsRef<str>>(&self, id: T) -> () {
+    /// Removes a plugin and all of its stored versions, unless its `on_uninstall` hook objects.
+    pub async fn deregister<T: AsRef<str>>(&self, id: T) -> InResult<()> {
         if let Some(plugin) = self.get(id.as_ref().to_string()).await {
+            plugin.run_hook(LifecycleHook::Uninstall, (), CallScope::default()).await?;
             let mut registry = self.backend.lock().await;
             let _ = self.fs.delete(plugin.file().id).await;
+            for version in plugin.versions() {
+                if version.source.id != plugin.file().id {
+                    let _ = self.fs.delete(version.source.id).await;
+                }
+            }
             let _ = self.documents.delete_many(
                 doc! { "metadata.id": id.as_ref().to_string() }
             ).await;
             let _ = registry.remove(&id.as_ref().to_string());
         }
+        Ok(())
     }
 
     pub async fn exists<T: AsRef<str>>(&self, id: T) -> bool {
@@ -450,3 +1589,114 @@ impl PluginRegistry {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(hosts: &[&str]) -> PluginMetadata {
+        PluginMetadata {
+            id: String::from("test"),
+            name: String::from("Test"),
+            version: String::from("1.0.0"),
+            allowed_hosts: hosts.iter().map(|h| h.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn config(options: Value) -> PluginConfiguration {
+        PluginConfiguration {
+            id: Id::default(),
+            plugin: String::from("test"),
+            icon: None,
+            name: String::from("Profile"),
+            options: serde_json::from_value(options).unwrap(),
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn saving_a_plugin_leaves_health_alone() {
+        let plugin = RegisteredPlugin {
+            id: Id::default(),
+            metadata: metadata(&[]),
+            source: FileInfo {
+                id: Id::default(),
+                original_filename: None,
+                content_type: String::from("application/wasm"),
+            },
+            url: None,
+            enabled: true,
+            limits: None,
+            approved_hosts: Vec::new(),
+            versions: Vec::new(),
+            update: None,
+            signature: SignatureStatus::default(),
+            validation: ValidationReport::default(),
+            health: None,
+        };
+        let fields = plugin.saved_fields().unwrap();
+        assert!(!fields.contains_key("health"));
+        assert!(!fields.contains_key("_id"));
+        assert_eq!(fields.get_bool("enabled"), Ok(true));
+    }
+
+    #[test]
+    fn limit_errors_are_classified_from_extism_errors() {
+        assert_eq!(classify_limit_error(&Error::msg("timeout")), Some(LimitError::Timeout));
+        assert_eq!(classify_limit_error(&Error::msg("oom")), Some(LimitError::Memory));
+        assert_eq!(classify_limit_error(&Error::msg("plugin ran out of fuel")), Some(LimitError::Fuel));
+        let http = Error::msg(format!("{HTTP_RESPONSE_LIMIT_ERROR}: 1024")).context("error while executing at wasm backtrace");
+        assert_eq!(classify_limit_error(&http), Some(LimitError::HttpResponse));
+    }
+
+    #[test]
+    fn other_errors_are_not_limit_errors() {
+        assert_eq!(classify_limit_error(&Error::msg("Returned non-zero exit code: 1")), None);
+        assert_eq!(classify_limit_error(&Error::msg("request timed out after a timeout")), None);
+    }
+
+    #[test]
+    fn compare_versions_orders_semver() {
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0.0", "1.0.0-beta.1"), Ordering::Greater);
+        assert_eq!(compare_versions("0.9.0", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0.0", "1.0.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn compare_versions_treats_unparsable_changes_as_newer() {
+        assert_eq!(compare_versions("nightly", "nightly"), Ordering::Equal);
+        assert_eq!(compare_versions("nightly-2", "nightly"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0.0", "nightly"), Ordering::Greater);
+        assert_eq!(compare_versions("nightly", "1.0.0"), Ordering::Greater);
+    }
+
+    #[test]
+    fn approve_hosts_drops_undeclared_hosts() {
+        let approved = approve_hosts(&metadata(&["api.example.com", "{host}"]), strings(&["{host}", "evil.example.com"]));
+        assert_eq!(approved, strings(&["{host}"]));
+    }
+
+    #[test]
+    fn expand_hosts_resolves_templates_per_profile() {
+        let configs = vec![
+            config(serde_json::json!({ "host": "https://media.example.com:8096/jellyfin" })),
+            config(serde_json::json!({ "host": "other.example.com" })),
+            config(serde_json::json!({ "host": "https://media.example.com" })),
+        ];
+        assert_eq!(
+            expand_hosts(&strings(&["api.example.com", "{host}"]), &configs),
+            strings(&["api.example.com", "media.example.com", "other.example.com"])
+        );
+    }
+
+    #[test]
+    fn expand_hosts_skips_missing_and_non_string_fields() {
+        let configs = vec![config(serde_json::json!({ "port": 8096 })), config(serde_json::json!({}))];
+        assert!(expand_hosts(&strings(&["{host}", "{port}"]), &configs).is_empty());
+    }
+}
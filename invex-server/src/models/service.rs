@@ -65,10 +65,10 @@ impl Service {
     }
 
     pub fn get_grant<T: AsRef<str>>(&self, id: T) -> Option<ServiceGrant> {
-        self.grants.get(&id.as_ref().to_string()).and_then(|v| Some(v.clone()))
+        self.grants.get(id.as_ref()).cloned()
     }
 
     pub fn remove_grant<T: AsRef<str>>(&mut self, id: T) {
-        self.grants.remove(&id.as_ref().to_string());
+        self.grants.remove(id.as_ref());
     }
 }
\ No newline at end of file
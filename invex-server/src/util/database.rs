@@ -1,5 +1,5 @@
 use std::{
-    fmt::Display, io::Write, ops::{Deref, DerefMut}
+    fmt::Display, ops::{Deref, DerefMut}
 };
 
 use bevy_reflect::{Reflect, TypeRegistration, Typed};
@@ -20,7 +20,6 @@ use rocket::{
     Request,
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use tempfile::NamedTempFile;
 use uuid::Uuid;
 
 use crate::models::error::ApiError;
@@ -77,17 +76,11 @@ impl<T: Document> Docs<T> {
     }
 
     pub async fn get<S: Into<String>>(&self, id: S) -> Option<T> {
-        match self.inner.find_one(doc! {"_id": id.into()}).await {
-            Ok(r) => r,
-            Err(_) => None,
-        }
+        self.inner.find_one(doc! {"_id": id.into()}).await.unwrap_or_default()
     }
 
     pub async fn query_one(&self, query: bson::Document) -> Option<T> {
-        match self.inner.find_one(query).await {
-            Ok(r) => r,
-            Err(_) => None,
-        }
+        self.inner.find_one(query).await.unwrap_or_default()
     }
 
     pub async fn query_many(&self, query: bson::Document) -> InResult<Vec<T>> {
@@ -109,7 +102,7 @@ impl<T: Document> Docs<T> {
             let cursor = self
                 .find(query.clone())
                 .skip(pagination.page * pagination.size)
-                .limit(i64::try_from(pagination.size).or::<()>(Ok(0)).unwrap())
+                .limit(i64::try_from(pagination.size).unwrap_or(0))
                 .await?;
             Ok(PaginationResult {
                 offset: pagination.page * pagination.size,
@@ -180,15 +173,15 @@ impl Deref for Id {
     }
 }
 
-impl Into<Bson> for Id {
-    fn into(self) -> Bson {
-        Bson::String(self.0)
+impl From<Id> for Bson {
+    fn from(val: Id) -> Self {
+        Bson::String(val.0)
     }
 }
 
-impl Into<String> for Id {
-    fn into(self) -> String {
-        self.0.clone()
+impl From<Id> for String {
+    fn from(val: Id) -> Self {
+        val.0
     }
 }
 
@@ -298,7 +291,7 @@ impl Fs {
         if let Ok(Some(document)) = self.0.find_one(doc! {"_id": id.to_string()}).await {
             if let Some(metadata) = document.metadata {
                 if let Ok(info) = bson::from_document::<FileInfo>(metadata) {
-                    Some(File::from_info(info, &self))
+                    Some(File::from_info(info, self))
                 } else {
                     None
                 }
@@ -341,7 +334,7 @@ impl<'r> FromFormField<'r> for File {
                         field.content_type.to_string(),
                         field
                             .file_name
-                            .and_then(|f| f.as_str().and_then(|s| Some(s.to_string()))),
+                            .and_then(|f| f.as_str().map(|s| s.to_string())),
                     )
                     .await
                 {
@@ -374,12 +367,6 @@ impl File {
     pub async fn read(&self) -> InResult<Vec<u8>> {
         self.fs.download(self.id.clone()).await
     }
-
-    pub async fn read_to_temp(&self) -> InResult<NamedTempFile> {
-        let mut file = NamedTempFile::new()?;
-        file.write_all(self.read().await?.as_slice())?;
-        Ok(file)
-    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Reflect)]
@@ -22,4 +22,16 @@ impl Deref for Conf {
     fn deref(&self) -> &Self::Target {
         &self.0
     }
-}
\ No newline at end of file
+}
+
+#[derive(Clone, Debug)]
+pub struct InviteCode(pub Option<String>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for InviteCode {
+    type Error = ApiError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(Self(request.headers().get_one("X-InvexCode").map(|c| c.to_string())))
+    }
+}
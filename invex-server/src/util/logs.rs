@@ -0,0 +1,58 @@
+use chrono::Utc;
+use mongodb::Database;
+use tokio::sync::mpsc::{ UnboundedReceiver, UnboundedSender };
+
+use crate::{ config::LogLevel, models::plugin::{ active_call, PluginLog } };
+
+use super::database::{ Docs, Id };
+
+/// Parses the level out of a formatted Extism log line, returning it with the rest of the line.
+fn parse_line(line: &str) -> (LogLevel, String) {
+    for (token, level) in [
+        ("ERROR", LogLevel::Error),
+        ("WARN", LogLevel::Warn),
+        ("INFO", LogLevel::Info),
+        ("DEBUG", LogLevel::Debug),
+        ("TRACE", LogLevel::Trace),
+    ] {
+        if let Some((_, message)) = line.split_once(&format!(" {token} ")) {
+            return (level, message.trim().to_string());
+        }
+    }
+    (LogLevel::Info, line.trim().to_string())
+}
+
+/// Log callback handed to Extism. Lines emitted during a plugin call are forwarded to be stored
+/// against that call; everything is still printed to stdout.
+pub fn capture(line: &str, sender: &UnboundedSender<PluginLog>) {
+    println!("{}", line.trim_end());
+    if let Some(call) = active_call() {
+        let (level, message) = parse_line(line);
+        let _ = sender.send(PluginLog {
+            id: Id::default(),
+            plugin: call.plugin,
+            method: call.method,
+            call_id: call.call_id,
+            level,
+            message,
+            timestamp: Utc::now(),
+        });
+    }
+}
+
+/// Creates the capped log collection if it doesn't exist yet.
+pub async fn create_collection(db: &Database, size: u64) {
+    let exists = db
+        .list_collection_names().await
+        .is_ok_and(|names| names.contains(&"plugin_log".to_string()));
+    if !exists {
+        let _ = db.create_collection("plugin_log").capped(true).size(size).await;
+    }
+}
+
+/// Drains captured log lines into the database until the sender side is dropped.
+pub async fn persist(mut receiver: UnboundedReceiver<PluginLog>, docs: Docs<PluginLog>) {
+    while let Some(entry) = receiver.recv().await {
+        let _ = docs.save(entry).await;
+    }
+}
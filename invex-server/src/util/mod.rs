@@ -1,6 +1,6 @@
 use std::{collections::HashMap, error::Error, sync::Arc};
 
-use extism::Plugin;
+use pool::PluginPool;
 use rocket::serde::json::Json;
 use tokio::sync::Mutex;
 
@@ -9,7 +9,12 @@ use crate::models::error::ApiError;
 pub mod database;
 pub mod crypto;
 pub mod guards;
+pub mod pool;
+pub mod logs;
+pub mod scheduler;
+pub mod signing;
+pub mod validation;
 
 pub type InResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
 pub type ApiResult<T> = Result<Json<T>, ApiError>;
-pub type PluginRegistryMap = Arc<Mutex<HashMap<String, Arc<Mutex<Plugin>>>>>;
\ No newline at end of file
+pub type PluginRegistryMap = Arc<Mutex<HashMap<String, Arc<PluginPool>>>>;
\ No newline at end of file
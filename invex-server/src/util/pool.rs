@@ -0,0 +1,99 @@
+use std::{ collections::HashSet, sync::{ Arc, Mutex as StdMutex }, time::Duration };
+
+use extism::Plugin as ExtismPlugin;
+use tokio::sync::{ OwnedSemaphorePermit, Semaphore };
+
+use super::InResult;
+
+pub type PluginFactory = Arc<dyn Fn() -> InResult<ExtismPlugin> + Send + Sync>;
+
+pub struct PluginPool {
+    factory: PluginFactory,
+    exports: HashSet<String>,
+    idle: StdMutex<Vec<ExtismPlugin>>,
+    permits: Arc<Semaphore>,
+    max_idle: usize,
+    checkout_timeout: Option<Duration>,
+}
+
+impl PluginPool {
+    pub fn new(
+        factory: PluginFactory,
+        exports: HashSet<String>,
+        max_instances: usize,
+        max_idle: usize,
+        checkout_timeout: Option<Duration>
+    ) -> Self {
+        PluginPool {
+            factory,
+            exports,
+            idle: StdMutex::new(Vec::new()),
+            permits: Arc::new(Semaphore::new(max_instances.max(1))),
+            max_idle,
+            checkout_timeout,
+        }
+    }
+
+    /// The optional exports the module provides, read once when it was compiled.
+    pub fn exports(&self) -> &HashSet<String> {
+        &self.exports
+    }
+
+    fn release(&self, instance: ExtismPlugin) {
+        if let Ok(mut idle) = self.idle.lock() {
+            if idle.len() < self.max_idle {
+                idle.push(instance);
+            }
+        }
+    }
+
+    pub async fn checkout(self: &Arc<Self>) -> InResult<PooledPlugin> {
+        let permit = match self.checkout_timeout {
+            Some(timeout) =>
+                tokio::time
+                    ::timeout(timeout, self.permits.clone().acquire_owned()).await
+                    .or(Err("Timed out waiting for a free plugin instance"))??,
+            None => self.permits.clone().acquire_owned().await?,
+        };
+        let instance = self.idle.lock().ok().and_then(|mut idle| idle.pop());
+        Ok(PooledPlugin {
+            instance,
+            discarded: false,
+            pool: self.clone(),
+            _permit: permit,
+        })
+    }
+}
+
+pub struct PooledPlugin {
+    instance: Option<ExtismPlugin>,
+    discarded: bool,
+    pool: Arc<PluginPool>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledPlugin {
+    /// Returns the checked-out instance, instantiating the compiled module if no idle instance
+    /// was available. This should be called from a blocking context.
+    pub fn instance(&mut self) -> InResult<&mut ExtismPlugin> {
+        if self.instance.is_none() {
+            self.instance = Some((self.pool.factory)()?);
+        }
+        Ok(self.instance.as_mut().unwrap())
+    }
+
+    /// Drops the instance instead of returning it to the pool, e.g. after it trapped.
+    pub fn discard(&mut self) {
+        self.discarded = true;
+    }
+}
+
+impl Drop for PooledPlugin {
+    fn drop(&mut self) {
+        if let Some(instance) = self.instance.take() {
+            if !self.discarded {
+                self.pool.release(instance);
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::{ collections::HashSet, str::FromStr, sync::{ Arc, Mutex }, time::Duration };
+
+use bson::doc;
+use chrono::{ DateTime, Utc };
+use invex_sdk::{ ScheduledTask, TaskConfig, TaskSchedule };
+
+use crate::models::plugin::{
+    Plugin,
+    PluginConfiguration,
+    PluginRegistry,
+    PluginTaskRun,
+    RegisteredPlugin,
+};
+
+use super::{ database::{ Docs, Id }, InResult };
+
+/// How often the scheduler looks for due tasks, which bounds how late a task can start.
+const TICK: Duration = Duration::from_secs(5);
+
+/// Parses a task's cron expression.
+pub fn parse_cron(expression: impl AsRef<str>) -> Result<cron::Schedule, String> {
+    cron::Schedule::from_str(expression.as_ref()).map_err(|e| e.to_string())
+}
+
+/// Whether a task is due at `now`, given when it last started. Tasks that have never run start
+/// right away on an interval, or at their next cron occurrence after the scheduler started.
+fn is_due(
+    schedule: &TaskSchedule,
+    last: Option<DateTime<Utc>>,
+    started: DateTime<Utc>,
+    now: DateTime<Utc>
+) -> bool {
+    match schedule {
+        TaskSchedule::Interval { seconds } =>
+            match last {
+                Some(last) => now - last >= chrono::Duration::seconds(*seconds as i64),
+                None => true,
+            }
+        TaskSchedule::Cron { expression } =>
+            match parse_cron(expression) {
+                Ok(cron) =>
+                    cron
+                        .after(&last.unwrap_or(started))
+                        .next()
+                        .is_some_and(|next| next <= now),
+                Err(_) => false,
+            }
+    }
+}
+
+/// Marks a task as running for as long as it is held, so the mark is cleared even if the task
+/// panics.
+struct RunningGuard {
+    running: Arc<Mutex<HashSet<String>>>,
+    key: String,
+}
+
+impl RunningGuard {
+    /// Marks the task as running, or returns `None` if it already is.
+    fn claim(running: &Arc<Mutex<HashSet<String>>>, key: String) -> Option<Self> {
+        let claimed = running.lock().unwrap_or_else(|e| e.into_inner()).insert(key.clone());
+        claimed.then(|| RunningGuard { running: running.clone(), key })
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.running.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.key);
+    }
+}
+
+/// Runs the scheduled tasks of enabled plugins. A task never runs twice at once under the same
+/// configuration profile; if it is still running when it comes due, that occurrence is skipped.
+#[derive(Clone)]
+pub struct Scheduler {
+    registry: PluginRegistry,
+    plugins: Docs<RegisteredPlugin>,
+    configs: Docs<PluginConfiguration>,
+    runs: Docs<PluginTaskRun>,
+    running: Arc<Mutex<HashSet<String>>>,
+    started: DateTime<Utc>,
+}
+
+impl Scheduler {
+    pub fn new(
+        registry: PluginRegistry,
+        plugins: Docs<RegisteredPlugin>,
+        configs: Docs<PluginConfiguration>,
+        runs: Docs<PluginTaskRun>
+    ) -> Self {
+        Scheduler {
+            registry,
+            plugins,
+            configs,
+            runs,
+            running: Arc::new(Mutex::new(HashSet::new())),
+            started: Utc::now(),
+        }
+    }
+
+    /// Checks for due tasks until the server shuts down.
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.tick().await {
+                println!("Failed to run scheduled tasks: {e}");
+            }
+            tokio::time::sleep(TICK).await;
+        }
+    }
+
+    async fn tick(&self) -> InResult<()> {
+        for info in self.plugins.query_many(doc! { "enabled": true }).await? {
+            if let Some(plugin) = self.registry.get(info.metadata.id.clone()).await {
+                for task in plugin.metadata().tasks {
+                    for config in self.targets(&plugin, &task).await? {
+                        self.start(&plugin, &task, config).await;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The configuration profiles a task should run under, where `None` runs it without one.
+    async fn targets(
+        &self,
+        plugin: &Plugin,
+        task: &ScheduledTask
+    ) -> InResult<Vec<Option<PluginConfiguration>>> {
+        Ok(match &task.config {
+            TaskConfig::None => vec![None],
+            TaskConfig::All =>
+                self.configs
+                    .query_many(doc! { "plugin": plugin.id() }).await?
+                    .into_iter()
+                    .map(Some)
+                    .collect(),
+            TaskConfig::Named { name } =>
+                self.configs
+                    .query_one(doc! { "plugin": plugin.id(), "name": name.clone() }).await
+                    .map(|c| vec![Some(c)])
+                    .unwrap_or_default(),
+        })
+    }
+
+    /// Starts a task in the background if it is due and not already running.
+    async fn start(&self, plugin: &Plugin, task: &ScheduledTask, config: Option<PluginConfiguration>) {
+        let config_id = config.as_ref().map(|c| c.id.clone());
+        let filter = PluginTaskRun::filter(plugin.id(), &task.key, config_id.as_ref());
+        let last = self.runs.query_one(filter).await;
+        if !is_due(&task.schedule, last.as_ref().map(|r| r.started_at), self.started, Utc::now()) {
+            return;
+        }
+
+        let key = format!(
+            "{}/{}/{}",
+            plugin.id(),
+            task.key,
+            config_id.map(|c| c.to_string()).unwrap_or_default()
+        );
+        let Some(guard) = RunningGuard::claim(&self.running, key) else {
+            return;
+        };
+
+        let (scheduler, plugin, task) = (self.clone(), plugin.clone(), task.clone());
+        tokio::spawn(async move {
+            let _guard = guard;
+            let mut run = plugin.run_task(&task, config).await;
+            run.id = last.map(|r| r.id).unwrap_or(Id::default());
+            if let Err(e) = scheduler.runs.save(run).await {
+                println!("Failed to record run of {}/{}: {e}", plugin.id(), task.key);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn interval_tasks_start_right_away_then_wait() {
+        let schedule = TaskSchedule::Interval { seconds: 60 };
+        let now = at("2024-01-01T12:00:00Z");
+        assert!(is_due(&schedule, None, now, now));
+        assert!(!is_due(&schedule, Some(at("2024-01-01T11:59:30Z")), now, now));
+        assert!(is_due(&schedule, Some(at("2024-01-01T11:59:00Z")), now, now));
+    }
+
+    #[test]
+    fn cron_tasks_wait_for_their_next_occurrence() {
+        let schedule = TaskSchedule::Cron { expression: String::from("0 0 * * * *") };
+        let started = at("2024-01-01T11:30:00Z");
+        assert!(!is_due(&schedule, None, started, at("2024-01-01T11:59:59Z")));
+        assert!(is_due(&schedule, None, started, at("2024-01-01T12:00:00Z")));
+        assert!(!is_due(&schedule, Some(at("2024-01-01T12:00:00Z")), started, at("2024-01-01T12:30:00Z")));
+    }
+
+    #[test]
+    fn invalid_cron_is_never_due() {
+        let schedule = TaskSchedule::Cron { expression: String::from("not cron") };
+        let now = at("2024-01-01T12:00:00Z");
+        assert!(!is_due(&schedule, None, now, now));
+    }
+
+    #[test]
+    fn running_guard_releases_its_task_when_dropped() {
+        let running = Arc::new(Mutex::new(HashSet::new()));
+        let guard = RunningGuard::claim(&running, String::from("task"));
+        assert!(guard.is_some());
+        assert!(RunningGuard::claim(&running, String::from("task")).is_none());
+        drop(guard);
+        assert!(RunningGuard::claim(&running, String::from("task")).is_some());
+    }
+}
@@ -0,0 +1,119 @@
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use ed25519_dalek::{ Signature, VerifyingKey };
+use serde::{ Deserialize, Serialize };
+
+use crate::config::{ PluginSigningConfig, UnsignedPolicy };
+
+use super::InResult;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SignatureStatus {
+    Verified {
+        publisher: String,
+    },
+
+    #[default]
+    Unsigned,
+
+    /// Unsigned, and accepted only because the unsigned policy is `warn`
+    UnsignedWarning,
+}
+
+/// Signatures may be uploaded either as the raw 64 bytes or as base64 text.
+fn parse_signature(signature: &[u8]) -> InResult<Signature> {
+    let bytes: [u8; 64] = if signature.len() == 64 {
+        signature.try_into()?
+    } else {
+        STANDARD
+            .decode(String::from_utf8_lossy(signature).trim())?
+            .try_into()
+            .or(Err("Signature must be 64 bytes"))?
+    };
+    Ok(Signature::from_bytes(&bytes))
+}
+
+fn parse_key(key: &str) -> InResult<VerifyingKey> {
+    let bytes: [u8; 32] = STANDARD
+        .decode(key.trim())?
+        .try_into()
+        .or(Err("Publisher key must be 32 bytes"))?;
+    Ok(VerifyingKey::from_bytes(&bytes)?)
+}
+
+/// Checks a plugin's detached signature against the trusted publisher keys. Invalid signatures are
+/// always rejected; unsigned plugins are handled according to the configured policy.
+pub fn verify(
+    data: &[u8],
+    signature: Option<&[u8]>,
+    config: &PluginSigningConfig
+) -> InResult<SignatureStatus> {
+    if let Some(signature) = signature {
+        let signature = parse_signature(signature)?;
+        for (publisher, key) in &config.trusted_keys {
+            match parse_key(key) {
+                Ok(key) => {
+                    if key.verify_strict(data, &signature).is_ok() {
+                        return Ok(SignatureStatus::Verified { publisher: publisher.clone() });
+                    }
+                }
+                Err(e) => println!("Ignoring invalid key for publisher {publisher}: {e}"),
+            }
+        }
+        Err("Plugin signature does not match any trusted publisher key".into())
+    } else {
+        match config.unsigned {
+            UnsignedPolicy::Reject => Err("Unsigned plugins are not allowed".into()),
+            UnsignedPolicy::Warn => Ok(SignatureStatus::UnsignedWarning),
+            UnsignedPolicy::Allow => Ok(SignatureStatus::Unsigned),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use ed25519_dalek::{ Signer, SigningKey };
+
+    use super::*;
+
+    const PLUGIN: &[u8] = b"\0asm plugin bytes";
+
+    fn config(unsigned: UnsignedPolicy) -> (SigningKey, PluginSigningConfig) {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let trusted_keys = HashMap::from([
+            (String::from("broken"), String::from("not a key")),
+            (String::from("acme"), STANDARD.encode(key.verifying_key().to_bytes())),
+        ]);
+        (key, PluginSigningConfig { unsigned, trusted_keys })
+    }
+
+    #[test]
+    fn accepts_raw_and_base64_signatures() {
+        let (key, config) = config(UnsignedPolicy::Reject);
+        let signature = key.sign(PLUGIN).to_bytes();
+        let verified = SignatureStatus::Verified { publisher: String::from("acme") };
+
+        assert_eq!(verify(PLUGIN, Some(&signature), &config).unwrap(), verified);
+        assert_eq!(verify(PLUGIN, Some(STANDARD.encode(signature).as_bytes()), &config).unwrap(), verified);
+    }
+
+    #[test]
+    fn rejects_signatures_from_untrusted_keys_or_for_other_data() {
+        let (key, config) = config(UnsignedPolicy::Allow);
+        let signature = key.sign(PLUGIN).to_bytes();
+        let stranger = SigningKey::from_bytes(&[9; 32]).sign(PLUGIN).to_bytes();
+
+        assert!(verify(b"tampered", Some(&signature), &config).is_err());
+        assert!(verify(PLUGIN, Some(&stranger), &config).is_err());
+        assert!(verify(PLUGIN, Some(b"garbage"), &config).is_err());
+    }
+
+    #[test]
+    fn unsigned_plugins_follow_the_policy() {
+        assert!(verify(PLUGIN, None, &config(UnsignedPolicy::Reject).1).is_err());
+        assert_eq!(verify(PLUGIN, None, &config(UnsignedPolicy::Warn).1).unwrap(), SignatureStatus::UnsignedWarning);
+        assert_eq!(verify(PLUGIN, None, &config(UnsignedPolicy::Allow).1).unwrap(), SignatureStatus::Unsigned);
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::HashSet;
+
+use invex_sdk::{
+    parse_date,
+    parse_datetime,
+    FieldType,
+    PluginArgument,
+    PluginDefinedMethodContext,
+    PluginMetadata,
+    TaskSchedule,
+};
+use regex::Regex;
+use serde::{ Deserialize, Serialize };
+
+use super::scheduler::parse_cron;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ValidationIssue {
+    pub severity: IssueSeverity,
+    pub path: String,
+    pub message: String,
+}
+
+/// Structural problems found in a plugin's metadata. Errors block registration, warnings are only reported.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        !self.issues.iter().any(|i| i.severity == IssueSeverity::Error)
+    }
+
+    pub fn summary(&self) -> String {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == IssueSeverity::Error)
+            .map(|i| format!("{}: {}", i.path, i.message))
+            .collect::<Vec<String>>()
+            .join("; ")
+    }
+
+    fn error(&mut self, path: impl AsRef<str>, message: impl AsRef<str>) {
+        self.issues.push(ValidationIssue {
+            severity: IssueSeverity::Error,
+            path: path.as_ref().to_string(),
+            message: message.as_ref().to_string(),
+        });
+    }
+
+    fn warning(&mut self, path: impl AsRef<str>, message: impl AsRef<str>) {
+        self.issues.push(ValidationIssue {
+            severity: IssueSeverity::Warning,
+            path: path.as_ref().to_string(),
+            message: message.as_ref().to_string(),
+        });
+    }
+
+    fn check_method(&mut self, path: impl AsRef<str>, method: &str, exists: &impl Fn(&str) -> bool) {
+        if !exists(method) {
+            self.error(path, format!("Method {method} is not exported by the plugin"));
+        }
+    }
+
+    fn check_keys<'a>(&mut self, path: impl AsRef<str>, keys: impl Iterator<Item = &'a String>) {
+        let mut seen = HashSet::new();
+        for key in keys {
+            if !seen.insert(key.to_lowercase()) {
+                self.error(format!("{}.{key}", path.as_ref()), "Duplicate key");
+            }
+        }
+    }
+
+    fn check_bounds(&mut self, path: impl AsRef<str>, field: &FieldType) {
+        let (min, max, valid): (_, _, fn(&str) -> bool) = match field {
+            FieldType::Date { min, max } =>
+                (min, max, |v| parse_date(v).is_some()),
+            FieldType::DateTime { min, max } =>
+                (min, max, |v| parse_datetime(v).is_some()),
+            _ => {
+                return;
+            }
+        };
+        for bound in [min, max].into_iter().flatten() {
+            if !valid(bound) {
+                self.error(&path, format!("Bound {bound} is not in the field's date format"));
+            }
+        }
+    }
+
+    fn check_text(&mut self, path: impl AsRef<str>, field: &FieldType) {
+        let (pattern, min_length, max_length) = match field {
+            FieldType::Text { validation, min_length, max_length, .. } =>
+                (validation.as_ref(), min_length, max_length),
+            FieldType::TextArea { min_length, max_length, .. } => (None, min_length, max_length),
+            _ => {
+                return;
+            }
+        };
+        if let Some(Err(e)) = pattern.map(|p| Regex::new(p)) {
+            self.error(&path, format!("Invalid validation pattern: {e}"));
+        }
+        if let (Some(min), Some(max)) = (min_length, max_length) {
+            if min > max {
+                self.error(&path, format!("Minimum length {min} is greater than maximum length {max}"));
+            }
+        }
+    }
+
+    fn check_fields(
+        &mut self,
+        path: impl AsRef<str>,
+        fields: &Vec<PluginArgument>,
+        context: PluginDefinedMethodContext,
+        exists: &impl Fn(&str) -> bool
+    ) {
+        let path = path.as_ref();
+        self.check_keys(path, fields.iter().map(|f| &f.key));
+        let siblings = fields
+            .iter()
+            .map(|f| f.key.as_str())
+            .collect::<HashSet<&str>>();
+        for field in fields {
+            let field_path = format!("{path}.{}", field.key);
+            for condition in [&field.visible_when, &field.required_when].into_iter().flatten() {
+                for key in condition.keys() {
+                    if key == field.key || !siblings.contains(key.as_str()) {
+                        self.error(
+                            &field_path,
+                            format!("Condition refers to {key}, which is not a sibling field")
+                        );
+                    }
+                }
+            }
+            self.check_bounds(&field_path, &field.field);
+            self.check_text(&field_path, &field.field);
+            if
+                matches!(field.field, FieldType::File { .. }) &&
+                !matches!(context, PluginDefinedMethodContext::Invite)
+            {
+                self.warning(&field_path, "File fields can only be uploaded as grant arguments");
+            }
+            if let FieldType::PluginDefined { method, context: field_context, .. } = &field.field {
+                if
+                    std::mem::discriminant(field_context) !=
+                    std::mem::discriminant(&context)
+                {
+                    self.error(
+                        &field_path,
+                        format!("Plugin-defined field must use the {context:?} context, not {field_context:?}")
+                    );
+                }
+                self.check_method(&field_path, method, exists);
+            }
+            if let Some(default) = &field.default {
+                if !field.validate(default.clone()) {
+                    self.error(&field_path, "Default value does not pass the field's own validation");
+                }
+            }
+        }
+    }
+}
+
+/// Checks plugin metadata for misplaced plugin-defined fields, duplicate keys, methods the module
+/// doesn't export, defaults that fail their own validation, conditions on unknown fields and task
+/// schedules that can't run.
+pub fn validate_metadata(metadata: &PluginMetadata, exists: impl Fn(&str) -> bool) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    report.check_fields("config", &metadata.config, PluginDefinedMethodContext::Plugin, &exists);
+    report.check_keys(
+        "grants",
+        metadata.grants.iter().map(|g| &g.key)
+    );
+
+    for grant in &metadata.grants {
+        let path = format!("grants.{}", grant.key);
+        report.check_method(&path, &grant.method, &exists);
+        if let Some(revoke) = &grant.revoke_method {
+            report.check_method(format!("{path}.revoke_method"), revoke, &exists);
+        }
+        report.check_fields(
+            format!("{path}.options"),
+            &grant.options,
+            PluginDefinedMethodContext::Service,
+            &exists
+        );
+        report.check_fields(
+            format!("{path}.arguments"),
+            &grant.arguments,
+            PluginDefinedMethodContext::Invite,
+            &exists
+        );
+
+        report.check_keys(
+            format!("{path}.admin_actions"),
+            grant.admin_actions.iter().map(|a| &a.key)
+        );
+        for action in &grant.admin_actions {
+            let action_path = format!("{path}.admin_actions.{}", action.key);
+            report.check_method(&action_path, &action.method, &exists);
+            report.check_fields(
+                format!("{action_path}.arguments"),
+                &action.arguments,
+                PluginDefinedMethodContext::Admin,
+                &exists
+            );
+        }
+    }
+
+    report.check_keys(
+        "tasks",
+        metadata.tasks.iter().map(|t| &t.key)
+    );
+    for task in &metadata.tasks {
+        let path = format!("tasks.{}", task.key);
+        report.check_method(&path, &task.method, &exists);
+        match &task.schedule {
+            TaskSchedule::Interval { seconds: 0 } =>
+                report.error(format!("{path}.schedule"), "Interval must be at least one second"),
+            TaskSchedule::Cron { expression } => {
+                if let Err(e) = parse_cron(expression) {
+                    report.error(format!("{path}.schedule"), format!("Invalid cron expression: {e}"));
+                }
+            }
+            _ => (),
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use invex_sdk::{
+        ExpectedType,
+        FieldBuilder,
+        FieldCondition,
+        GrantActionBuilder,
+        ScheduledTaskBuilder,
+    };
+    use serde_json::Value;
+
+    use super::*;
+
+    fn text() -> FieldType {
+        FieldType::Text { placeholder: None, password: false, validation: None, min_length: None, max_length: None }
+    }
+
+    fn field(key: &str, field: FieldType) -> FieldBuilder {
+        FieldBuilder::minimal(key, key, field)
+    }
+
+    fn metadata(config: Vec<PluginArgument>) -> PluginMetadata {
+        PluginMetadata {
+            id: String::from("test"),
+            name: String::from("Test"),
+            version: String::from("1.0.0"),
+            config,
+            ..Default::default()
+        }
+    }
+
+    fn issues(report: &ValidationReport) -> Vec<(IssueSeverity, &str)> {
+        report.issues.iter().map(|i| (i.severity.clone(), i.path.as_str())).collect()
+    }
+
+    #[test]
+    fn well_formed_metadata_has_no_issues() {
+        let mut meta = metadata(vec![field("host", text()).build().unwrap()]);
+        meta.grants.push(GrantActionBuilder::minimal("account", "create_account", "Account").build().unwrap());
+        meta.tasks.push(
+            ScheduledTaskBuilder::minimal("sync", "sync", "Sync", TaskSchedule::Interval { seconds: 60 }).build().unwrap()
+        );
+        let report = validate_metadata(&meta, |_| true);
+        assert!(report.issues.is_empty());
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn duplicate_keys_and_missing_methods_are_errors() {
+        let mut meta = metadata(vec![field("host", text()).build().unwrap(), field("host", text()).build().unwrap()]);
+        meta.grants.push(GrantActionBuilder::minimal("account", "create_account", "Account").build().unwrap());
+        let report = validate_metadata(&meta, |name| name != "create_account");
+        assert_eq!(issues(&report), [(IssueSeverity::Error, "config.host"), (IssueSeverity::Error, "grants.account")]);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn plugin_defined_fields_must_match_their_context() {
+        let kind = FieldType::PluginDefined {
+            method: String::from("list_libraries"),
+            context: PluginDefinedMethodContext::Service,
+            expected_type: ExpectedType::StringArray,
+        };
+        let report = validate_metadata(&metadata(vec![field("libraries", kind).build().unwrap()]), |_| true);
+        assert_eq!(issues(&report), [(IssueSeverity::Error, "config.libraries")]);
+    }
+
+    #[test]
+    fn plugin_defined_defaults_must_match_the_expected_type() {
+        let kind = FieldType::PluginDefined {
+            method: String::from("list_libraries"),
+            context: PluginDefinedMethodContext::Plugin,
+            expected_type: ExpectedType::StringArray,
+        };
+        let meta = metadata(vec![field("libraries", kind).default(Value::from("movies")).build().unwrap()]);
+        let report = validate_metadata(&meta, |_| true);
+        assert_eq!(issues(&report), [(IssueSeverity::Error, "config.libraries")]);
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn invalid_field_settings_are_reported() {
+        let meta = metadata(vec![
+            field("name", FieldType::Text { placeholder: None, password: false, validation: Some(String::from("(")), min_length: Some(5), max_length: Some(2) })
+                .build().unwrap(),
+            field("since", FieldType::Date { min: Some(String::from("yesterday")), max: None }).build().unwrap(),
+            field("tls", FieldType::Switch {}).default(Value::from("yes")).build().unwrap(),
+            field("upload", FieldType::File { content_types: Vec::new(), max_size: None }).build().unwrap(),
+            field("cert", text()).visible_when(FieldCondition::equals("missing", true)).build().unwrap(),
+        ]);
+        let report = validate_metadata(&meta, |_| true);
+        assert_eq!(issues(&report), [
+            (IssueSeverity::Error, "config.name"),
+            (IssueSeverity::Error, "config.name"),
+            (IssueSeverity::Error, "config.since"),
+            (IssueSeverity::Error, "config.tls"),
+            (IssueSeverity::Warning, "config.upload"),
+            (IssueSeverity::Error, "config.cert"),
+        ]);
+    }
+
+    #[test]
+    fn task_schedules_must_be_runnable() {
+        let mut meta = metadata(Vec::new());
+        meta.tasks.push(
+            ScheduledTaskBuilder::minimal("often", "sync", "Sync", TaskSchedule::Interval { seconds: 0 }).build().unwrap()
+        );
+        meta.tasks.push(
+            ScheduledTaskBuilder::minimal("never", "sync", "Sync", TaskSchedule::Cron { expression: String::from("nope") })
+                .build().unwrap()
+        );
+        let report = validate_metadata(&meta, |_| true);
+        assert_eq!(issues(&report), [(IssueSeverity::Error, "tasks.often.schedule"), (IssueSeverity::Error, "tasks.never.schedule")]);
+    }
+
+    #[test]
+    fn warnings_alone_keep_metadata_valid() {
+        let meta = metadata(vec![field("upload", FieldType::File { content_types: Vec::new(), max_size: None }).build().unwrap()]);
+        let report = validate_metadata(&meta, |_| true);
+        assert_eq!(issues(&report), [(IssueSeverity::Warning, "config.upload")]);
+        assert!(report.is_valid());
+        assert!(report.summary().is_empty());
+    }
+}
@@ -1,8 +1,8 @@
 use extism_pdk::*;
 use invex_sdk::{
-    params::{GrantActionParams, PluginFieldParams}, ExpectedType, FieldBuilder, FieldSelectOption, FieldType, GrantActionBuilder, GrantResource, HashedPassword, PluginDefinedMethodContext, PluginMetadata, PluginMetadataBuilder
+    call::{ActionResult, ConfigTestResult, HealthCheckResult}, params::{AdminActionParams, ConfigTestParams, HealthCheckParams, GrantActionParams, GrantRevokeParams, PluginFieldParams, ResourceActionParams}, AdminActionBuilder, FieldSelectOption, FieldType, GrantActionBuilder, GrantResource, HashedPassword, PluginFields, PluginMetadata, PluginMetadataBuilder
 };
-use models::{ CreateUserArguments, CreateUserConfig, JellyfinPluginConfig, LibraryReference, UserItem };
+use models::{ CreateUserArguments, CreateUserConfig, JellyfinPluginConfig, LibraryReference, ResetPasswordArguments, ResetPasswordMetadata, SetDisabledArguments, UserItem };
 use net::Connection;
 use serde_json::json;
 mod net;
@@ -19,40 +19,8 @@ pub fn metadata() -> FnResult<Json<PluginMetadata>> {
                 .url(
                     "https://github.com/dax-dot-gay/Invex/blob/main/plugin_builds/invex_plugin_jellyfin.wasm"
                 )
-                .with_config(
-                    FieldBuilder::minimal("host", "Jellyfin Instance URL", FieldType::Text {
-                        placeholder: Some("https://jellyfin.your.server".to_string()),
-                        password: false,
-                        validation: None,
-                    })
-                        .required(true)
-                        .icon("icon:IconServer")
-                        .build()?
-                )
-                .with_config(
-                    FieldBuilder::minimal("api_key", "API Key", FieldType::Text {
-                        placeholder: Some("API Key".to_string()),
-                        password: true,
-                        validation: None,
-                    })
-                        .required(true)
-                        .icon("icon:IconKeyFilled")
-                        .build()?
-                )
-                .with_config(
-                    FieldBuilder::minimal(
-                        "server_name",
-                        "Jellyfin Instance Display Name",
-                        FieldType::Text {
-                            placeholder: Some("My Homeserver".to_string()),
-                            password: false,
-                            validation: None,
-                        }
-                    )
-                        .required(true)
-                        .icon("icon:IconLabelFilled")
-                        .build()?
-                )
+                .with_allowed_host("{host}")
+                .config(JellyfinPluginConfig::fields())
                 .with_grant(
                     GrantActionBuilder::minimal(
                         "create_user",
@@ -61,38 +29,28 @@ pub fn metadata() -> FnResult<Json<PluginMetadata>> {
                     )
                         .description("Adds a normal (non-admin) user to the instance.")
                         .icon("icon:IconUserPlus")
-                        .with_option(
-                            FieldBuilder::minimal(
-                                "libraries",
-                                "Library Access",
-                                FieldType::PluginDefined {
-                                    method: String::from("util_get_libraries"),
-                                    context: PluginDefinedMethodContext::Service,
-                                    expected_type: ExpectedType::StringArray,
-                                }
+                        .options(CreateUserConfig::fields())
+                        .arguments(CreateUserArguments::fields())
+                        .with_admin_action(
+                            AdminActionBuilder::minimal(
+                                "set_disabled",
+                                "admin_set_disabled",
+                                "Enable/Disable User"
                             )
-                                .required(true)
-                                .icon("icon:IconLibraryPhoto")
+                                .description("Enables or disables the user's Jellyfin account.")
+                                .icon("icon:IconUserOff")
+                                .arguments(SetDisabledArguments::fields())
                                 .build()?
                         )
-                        .with_argument(
-                            FieldBuilder::minimal("username", "Username", FieldType::Text {
-                                placeholder: Some(String::from("Blackbeard")),
-                                password: false,
-                                validation: None,
-                            })
-                                .required(true)
-                                .icon("icon:IconUser")
-                                .build()?
-                        )
-                        .with_argument(
-                            FieldBuilder::minimal("password", "Password", FieldType::Text {
-                                placeholder: Some(String::from("super-secret")),
-                                password: true,
-                                validation: None,
-                            })
-                                .required(true)
+                        .with_admin_action(
+                            AdminActionBuilder::minimal(
+                                "reset_password",
+                                "admin_reset_password",
+                                "Reset Password"
+                            )
+                                .description("Sets a new password for the user's Jellyfin account.")
                                 .icon("icon:IconLockFilled")
+                                .arguments(ResetPasswordArguments::fields())
                                 .build()?
                         )
                         .revoke_method("revoke_create_user")
@@ -103,11 +61,45 @@ pub fn metadata() -> FnResult<Json<PluginMetadata>> {
     )
 }
 
+#[plugin_fn]
+pub fn test_config(params: Json<ConfigTestParams>) -> FnResult<Json<ConfigTestResult>> {
+    let config = JellyfinPluginConfig::from_params(&params.into_inner().plugin_config).or(Err(WithReturnCode(Error::msg("Invalid plugin config"), 422)))?;
+    let connection: Connection = config.into();
+    match connection.get("/System/Info") {
+        Ok(response) => match response.status_code() {
+            200..=299 => {
+                let info = response.json::<serde_json::Value>().unwrap_or_default();
+                let name = info.get("ServerName").and_then(|v| v.as_str()).unwrap_or("Jellyfin");
+                let version = info.get("Version").and_then(|v| v.as_str()).unwrap_or("unknown version");
+                Ok(Json(ConfigTestResult::success(format!("Connected to {name} ({version})"))))
+            },
+            401 | 403 => Ok(Json(ConfigTestResult::failure("Jellyfin rejected the API key").with_field("api_key", "API key was rejected"))),
+            code => Ok(Json(ConfigTestResult::failure(format!("Jellyfin responded with status {code}")).with_field("host", "Doesn't look like a Jellyfin server")))
+        },
+        Err(e) => Ok(Json(ConfigTestResult::failure(format!("Failed to reach Jellyfin: {e}")).with_field("host", "Server is unreachable")))
+    }
+}
+
+#[plugin_fn]
+pub fn healthcheck(params: Json<HealthCheckParams>) -> FnResult<Json<HealthCheckResult>> {
+    // Without a config profile there's no server to reach
+    let Some(plugin_config) = params.into_inner().plugin_config else {
+        return Ok(Json(HealthCheckResult { healthy: true, message: None }));
+    };
+    let config = JellyfinPluginConfig::from_params(&plugin_config).or(Err(WithReturnCode(Error::msg("Invalid plugin config"), 422)))?;
+    let connection: Connection = config.into();
+    match connection.get("/System/Info") {
+        Ok(response) if (200..300).contains(&response.status_code()) => Ok(Json(HealthCheckResult { healthy: true, message: None })),
+        Ok(response) => Ok(Json(HealthCheckResult { healthy: false, message: Some(format!("Jellyfin responded with status {}", response.status_code())) })),
+        Err(e) => Ok(Json(HealthCheckResult { healthy: false, message: Some(format!("Failed to reach Jellyfin: {e}")) }))
+    }
+}
+
 #[plugin_fn]
 pub fn util_get_libraries(params: Json<PluginFieldParams>) -> FnResult<Json<FieldType>> {
     let param_item = params.into_inner();
     if let PluginFieldParams::ServiceConfig { plugin_config } = param_item.clone() {
-        if let Ok(config) = plugin_config.resolve::<JellyfinPluginConfig>() {
+        if let Ok(config) = JellyfinPluginConfig::from_params(&plugin_config) {
             let connection: Connection = config.into();
             match connection.get("/Library/VirtualFolders") {
                 Ok(response) => {
@@ -149,15 +141,15 @@ pub fn util_get_libraries(params: Json<PluginFieldParams>) -> FnResult<Json<Fiel
 #[plugin_fn]
 pub fn grant_create_user(params: Json<GrantActionParams>) -> FnResult<Json<Vec<GrantResource>>> {
     let action_params = params.into_inner();
-    let plugin_config = action_params.plugin_config.resolve::<JellyfinPluginConfig>().or(Err(WithReturnCode(Error::msg("Invalid plugin config"), 422)))?;
-    let service_config = action_params.service_config.resolve::<CreateUserConfig>().or(Err(WithReturnCode(Error::msg("Invalid service config"), 422)))?;
-    let user_arguments = action_params.user_arguments.resolve::<CreateUserArguments>().or(Err(WithReturnCode(Error::msg("Invalid user arguments"), 422)))?;
+    let plugin_config = JellyfinPluginConfig::from_params(&action_params.plugin_config).or(Err(WithReturnCode(Error::msg("Invalid plugin config"), 422)))?;
+    let service_config = CreateUserConfig::from_params(&action_params.service_config).or(Err(WithReturnCode(Error::msg("Invalid service config"), 422)))?;
+    let user_arguments = CreateUserArguments::from_params(&action_params.user_arguments).or(Err(WithReturnCode(Error::msg("Invalid user arguments"), 422)))?;
     let dry_run = action_params.dry_run;
 
     let connection: Connection = plugin_config.into();
     match connection.get("/Users") {
         Ok(response) => {
-            match response.json::<Vec<UserItem>>().and_then(|v| Ok(v.iter().map(|i| i.name.clone()).collect::<Vec<String>>())) {
+            match response.json::<Vec<UserItem>>().map(|v| v.iter().map(|i| i.name.clone()).collect::<Vec<String>>()) {
                 Ok(existing) =>  {
                 if existing.contains(&user_arguments.username) {
                     return Err(WithReturnCode(Error::msg("Desired username already exists"), 405));
@@ -174,9 +166,21 @@ pub fn grant_create_user(params: Json<GrantActionParams>) -> FnResult<Json<Vec<G
                                 match connection.post(format!("/Users/{}/Policy", created.id.clone()), Some(json!({
                                     "AuthenticationProviderId": created.policy.authentication_provider_id.clone(),
                                     "PasswordResetProviderId": created.policy.password_reset_provider_id.clone(),
-                                    "EnabledFolders": service_config.libraries.clone()
+                                    "EnableAllFolders": service_config.all_libraries,
+                                    "EnabledFolders": service_config.libraries.clone().unwrap_or_default()
                                 }))) {
-                                    Ok(_) => Ok(Json(vec![GrantResource::Account { id: created.id.clone(), user_id: Some(created.id.clone()), username: Some(created.name.clone()), email: None, password: Some(hashed_pw.clone()), metadata: None }])),
+                                    Ok(_) => Ok(Json(vec![
+                                        GrantResource::Account { id: created.id.clone(), user_id: Some(created.id.clone()), username: Some(created.name.clone()), email: None, password: Some(hashed_pw.clone()), metadata: None },
+                                        GrantResource::Action {
+                                            id: format!("{}_reset_password", created.id),
+                                            metadata: Some(json!({"user_id": created.id.clone()})),
+                                            method: String::from("action_reset_password"),
+                                            label: String::from("Reset Password"),
+                                            arguments: ResetPasswordArguments::fields(),
+                                            description: Some(String::from("Sets a new password for your Jellyfin account.")),
+                                            icon: Some(String::from("icon:IconLockFilled"))
+                                        }
+                                    ])),
                                     Err(e) => Err(WithReturnCode(Error::msg(format!("Failed to add user to libraries: {e:?}")), 500))
                                 }
                             } else {
@@ -193,3 +197,101 @@ pub fn grant_create_user(params: Json<GrantActionParams>) -> FnResult<Json<Vec<G
         Err(e) => Err(WithReturnCode(Error::msg(format!("Failed to retrieve list of existing users: {e:?}")), 500))
     }
 }
+
+
+#[plugin_fn]
+pub fn revoke_create_user(params: Json<GrantRevokeParams>) -> FnResult<Json<()>> {
+    let revoke_params = params.into_inner();
+    let plugin_config = JellyfinPluginConfig::from_params(&revoke_params.plugin_config).or(Err(WithReturnCode(Error::msg("Invalid plugin config"), 422)))?;
+    let connection: Connection = plugin_config.into();
+
+    for resource in revoke_params.resources {
+        if let GrantResource::Account { user_id: Some(user_id), .. } = resource {
+            match connection.delete(format!("/Users/{user_id}")) {
+                Ok(response) => {
+                    if response.status_code() >= 400 && response.status_code() != 404 {
+                        return Err(WithReturnCode(Error::msg(format!("Failed to delete user: {}", response.status_code())), 500));
+                    }
+                },
+                Err(e) => return Err(WithReturnCode(Error::msg(format!("Failed to delete user: {e:?}")), 500))
+            }
+        }
+    }
+
+    Ok(Json(()))
+}
+
+fn account_user_id(resource: &GrantResource) -> Result<String, WithReturnCode<Error>> {
+    if let GrantResource::Account { user_id: Some(user_id), .. } = resource {
+        Ok(user_id.clone())
+    } else {
+        Err(WithReturnCode(Error::msg("Resource is not a Jellyfin account"), 422))
+    }
+}
+
+#[plugin_fn]
+pub fn admin_set_disabled(params: Json<AdminActionParams>) -> FnResult<Json<ActionResult>> {
+    let action_params = params.into_inner();
+    let plugin_config = JellyfinPluginConfig::from_params(&action_params.plugin_config).or(Err(WithReturnCode(Error::msg("Invalid plugin config"), 422)))?;
+    let arguments = SetDisabledArguments::from_params(&action_params.arguments).or(Err(WithReturnCode(Error::msg("Invalid action arguments"), 422)))?;
+    let user_id = account_user_id(&action_params.resource)?;
+
+    let connection: Connection = plugin_config.into();
+    let user = connection.get(format!("/Users/{user_id}"))
+        .and_then(|response| response.json::<UserItem>())
+        .map_err(|e| WithReturnCode(Error::msg(format!("Failed to retrieve user: {e:?}")), 500))?;
+    match connection.post(format!("/Users/{user_id}/Policy"), Some(json!({
+        "AuthenticationProviderId": user.policy.authentication_provider_id.clone(),
+        "PasswordResetProviderId": user.policy.password_reset_provider_id.clone(),
+        "EnabledFolders": user.policy.enabled_folders.clone(),
+        "IsDisabled": arguments.disabled
+    }))) {
+        Ok(_) => Ok(Json(ActionResult {
+            message: Some(String::from(if arguments.disabled { "User disabled" } else { "User enabled" })),
+            ..Default::default()
+        })),
+        Err(e) => Err(WithReturnCode(Error::msg(format!("Failed to update user policy: {e:?}")), 500))
+    }
+}
+
+#[plugin_fn]
+pub fn admin_reset_password(params: Json<AdminActionParams>) -> FnResult<Json<ActionResult>> {
+    let action_params = params.into_inner();
+    let plugin_config = JellyfinPluginConfig::from_params(&action_params.plugin_config).or(Err(WithReturnCode(Error::msg("Invalid plugin config"), 422)))?;
+    let arguments = ResetPasswordArguments::from_params(&action_params.arguments).or(Err(WithReturnCode(Error::msg("Invalid action arguments"), 422)))?;
+    let user_id = account_user_id(&action_params.resource)?;
+
+    let connection: Connection = plugin_config.into();
+    reset_password(&connection, &user_id, &arguments.password)?;
+    let updated = match action_params.resource {
+        GrantResource::Account { id, user_id, username, email, metadata, .. } => GrantResource::Account { id, user_id, username, email, password: Some(HashedPassword::new(arguments.password.clone())), metadata },
+        other => other
+    };
+    Ok(Json(ActionResult {
+        message: Some(String::from("Password reset")),
+        resource: Some(updated),
+        ..Default::default()
+    }))
+}
+
+#[plugin_fn]
+pub fn action_reset_password(params: Json<ResourceActionParams>) -> FnResult<Json<ActionResult>> {
+    let action_params = params.into_inner();
+    let plugin_config = JellyfinPluginConfig::from_params(&action_params.plugin_config).or(Err(WithReturnCode(Error::msg("Invalid plugin config"), 422)))?;
+    let arguments = ResetPasswordArguments::from_params(&action_params.arguments).or(Err(WithReturnCode(Error::msg("Invalid action arguments"), 422)))?;
+    let metadata = action_params.resource.metadata::<ResetPasswordMetadata>().ok_or(WithReturnCode(Error::msg("Invalid action metadata"), 422))?;
+
+    let connection: Connection = plugin_config.into();
+    reset_password(&connection, &metadata.user_id, &arguments.password)?;
+    Ok(Json(ActionResult {
+        message: Some(String::from("Password reset")),
+        ..Default::default()
+    }))
+}
+
+fn reset_password(connection: &Connection, user_id: &str, password: &str) -> Result<(), WithReturnCode<Error>> {
+    match connection.post(format!("/Users/{user_id}/Password"), Some(json!({"NewPw": password, "ResetPassword": false}))) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(WithReturnCode(Error::msg(format!("Failed to reset password: {e:?}")), 500))
+    }
+}
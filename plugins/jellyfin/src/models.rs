@@ -1,9 +1,15 @@
+use invex_sdk::{FieldCondition, PluginFields};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PluginFields)]
 pub struct JellyfinPluginConfig {
+    #[field(label = "Jellyfin Instance URL", placeholder = "https://jellyfin.your.server", icon = "icon:IconServer")]
     pub host: String,
+
+    #[field(label = "API Key", placeholder = "API Key", password, icon = "icon:IconKeyFilled")]
     pub api_key: String,
+
+    #[field(label = "Jellyfin Instance Display Name", placeholder = "My Homeserver", icon = "icon:IconLabelFilled")]
     pub server_name: String
 }
 
@@ -16,17 +22,48 @@ pub struct LibraryReference {
     pub name: String
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PluginFields)]
 pub struct CreateUserConfig {
-    pub libraries: Vec<String>
+    #[field(label = "Grant All Libraries", icon = "icon:IconLibrary", required = false, default = false)]
+    pub all_libraries: bool,
+
+    #[field(
+        label = "Library Access",
+        plugin_defined = "util_get_libraries",
+        context = "service",
+        icon = "icon:IconLibraryPhoto",
+        visible_when = FieldCondition::equals("all_libraries", false),
+        required_when = FieldCondition::equals("all_libraries", false)
+    )]
+    pub libraries: Option<Vec<String>>
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PluginFields)]
 pub struct CreateUserArguments {
+    #[field(label = "Username", placeholder = "Blackbeard", icon = "icon:IconUser", validation = r"^[\w\-'.@]+$", max_length = 64)]
     pub username: String,
+
+    #[field(label = "Password", placeholder = "super-secret", password, icon = "icon:IconLockFilled")]
     pub password: String
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PluginFields)]
+pub struct SetDisabledArguments {
+    #[field(label = "Disabled", icon = "icon:IconUserOff")]
+    pub disabled: bool
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PluginFields)]
+pub struct ResetPasswordArguments {
+    #[field(label = "New Password", placeholder = "super-secret", password, icon = "icon:IconLockFilled")]
+    pub password: String
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ResetPasswordMetadata {
+    pub user_id: String
+}
+
 #[allow(dead_code)]
 #[derive(Deserialize)]
 #[serde(rename_all="PascalCase")]
@@ -1,4 +1,5 @@
-use extism_pdk::{debug, http::request, Error, HttpRequest, HttpResponse, ToMemory};
+use extism_pdk::{debug, Error, HttpRequest, ToBytes};
+use invex_sdk::http::{request, HttpResponse};
 
 use crate::models::JellyfinPluginConfig;
 
@@ -50,7 +51,7 @@ impl Connection {
         }
     }
 
-    pub fn post<T: ToMemory>(&self, endpoint: impl AsRef<str>, data: Option<T>) -> Result<HttpResponse, Error> {
+    pub fn post<'a, T: ToBytes<'a>>(&self, endpoint: impl AsRef<str>, data: Option<T>) -> Result<HttpResponse, Error> {
         let req = HttpRequest::new(self.url(endpoint)).with_method("POST").with_header("Authorization", self.auth()).with_header("Content-Type", "application/json");
         debug!("POST: {}", serde_json::to_string_pretty(&req).unwrap_or_default().to_string());
         match request::<T>(&req, data) {
@@ -71,3 +72,74 @@ impl From<JellyfinPluginConfig> for Connection {
         Connection::new(&value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use invex_sdk::{http::HttpResponse, testing};
+    use serde_json::json;
+
+    use super::Connection;
+    use crate::models::{JellyfinPluginConfig, UserItem};
+
+    fn connection() -> Connection {
+        JellyfinPluginConfig {
+            host: String::from("https://jellyfin.test/"),
+            api_key: String::from("secret"),
+            server_name: String::from("Test"),
+        }
+        .into()
+    }
+
+    fn user(name: &str, id: &str) -> serde_json::Value {
+        json!({
+            "Name": name,
+            "Id": id,
+            "Policy": {
+                "IsAdministrator": false,
+                "EnabledFolders": [],
+                "AuthenticationProviderId": "auth",
+                "PasswordResetProviderId": "reset"
+            }
+        })
+    }
+
+    #[test]
+    fn get_lists_existing_users_with_auth() {
+        testing::reset();
+        testing::mock_http("GET", "https://jellyfin.test/Users", HttpResponse::json_body(200, &json!([user("alice", "1"), user("bob", "2")])));
+
+        let users = connection().get("/Users").unwrap().json::<Vec<UserItem>>().unwrap();
+        assert_eq!(users.iter().map(|u| u.name.as_str()).collect::<Vec<_>>(), vec!["alice", "bob"]);
+
+        let requests = testing::requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].url, "https://jellyfin.test/Users");
+        assert_eq!(requests[0].headers.get("Authorization").map(String::as_str), Some("MediaBrowser Token=\"secret\""));
+    }
+
+    #[test]
+    fn post_sends_json_body() {
+        testing::reset();
+        testing::mock_http("POST", "https://jellyfin.test/Users/New", HttpResponse::json_body(200, &user("carol", "3")));
+
+        let created = connection()
+            .post("Users/New", Some(json!({"Name": "carol", "Password": "pw"})))
+            .unwrap()
+            .json::<UserItem>()
+            .unwrap();
+        assert_eq!(created.id, "3");
+
+        let requests = testing::requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].json::<serde_json::Value>(), Some(json!({"Name": "carol", "Password": "pw"})));
+    }
+
+    #[test]
+    fn unreachable_upstream_is_an_error() {
+        testing::reset();
+        testing::mock_http_error("DELETE", "https://jellyfin.test/Users/*", "connection refused");
+
+        assert!(connection().delete("/Users/1").is_err());
+        assert!(connection().get("/Users").is_err());
+    }
+}
@@ -1,14 +1,10 @@
 use extism_pdk::*;
 use invex_sdk::{
-    params::GrantActionParams, FieldBuilder, FieldSelectOption, GrantActionBuilder,
-    GrantResource, HashedPassword, PluginFileData, PluginMetadata, PluginMetadataBuilder,
+    fs::fs_store, params::GrantActionParams, FieldBuilder, FieldSelectOption,
+    GrantActionBuilder, GrantResource, HashedPassword, PluginFileData, PluginMetadata,
+    PluginMetadataBuilder,
 };
 
-#[host_fn]
-extern "ExtismHost" {
-    fn fs_store(data: Json<PluginFileData>) -> String;
-}
-
 #[plugin_fn]
 pub fn metadata() -> FnResult<Json<PluginMetadata>> {
     Ok(Json(
@@ -24,7 +20,9 @@ pub fn metadata() -> FnResult<Json<PluginMetadata>> {
                     invex_sdk::FieldType::Text {
                         placeholder: Some(String::from("An Example")),
                         password: false,
-                        validation: None,
+                        validation: Some(String::from("^[A-Z!]+$")),
+                        min_length: Some(2),
+                        max_length: Some(32),
                     },
                 )
                 .default("BEANS!")
@@ -84,14 +82,84 @@ pub fn metadata() -> FnResult<Json<PluginMetadata>> {
                     invex_sdk::FieldType::TextArea {
                         lines: Some(4),
                         placeholder: None,
+                        min_length: None,
+                        max_length: Some(500),
+                    },
+                )
+                .required(false)
+                .build()?,
+            )
+            .with_config(
+                FieldBuilder::minimal(
+                    "example-date",
+                    "Example Field Date",
+                    invex_sdk::FieldType::Date {
+                        min: Some(String::from("2000-01-01")),
+                        max: None,
                     },
                 )
                 .required(false)
+                .icon("icon:IconCalendar")
+                .build()?,
+            )
+            .with_config(
+                FieldBuilder::minimal(
+                    "example-datetime",
+                    "Example Field DateTime",
+                    invex_sdk::FieldType::DateTime { min: None, max: None },
+                )
+                .required(false)
+                .build()?,
+            )
+            .with_config(
+                FieldBuilder::minimal(
+                    "example-email",
+                    "Example Field Email",
+                    invex_sdk::FieldType::Email { placeholder: None },
+                )
+                .required(false)
+                .icon("icon:IconMail")
+                .build()?,
+            )
+            .with_config(
+                FieldBuilder::minimal(
+                    "example-url",
+                    "Example Field URL",
+                    invex_sdk::FieldType::Url {
+                        placeholder: Some(String::from("https://example.com")),
+                        schemes: Vec::new(),
+                    },
+                )
+                .required(false)
+                .icon("icon:IconLink")
+                .build()?,
+            )
+            .with_config(
+                FieldBuilder::minimal(
+                    "example-color",
+                    "Example Field Color",
+                    invex_sdk::FieldType::Color {},
+                )
+                .default("#ff00ff")
+                .required(false)
                 .build()?,
             )
             .with_grant(
                 GrantActionBuilder::minimal("test", "grant_test", "Test Grant")
                     .icon("icon:IconFlask")
+                    .with_argument(
+                        FieldBuilder::minimal(
+                            "example-file",
+                            "Example Field File",
+                            invex_sdk::FieldType::File {
+                                content_types: vec![String::from("text/*")],
+                                max_size: Some(64 * 1024),
+                            },
+                        )
+                        .required(false)
+                        .icon("icon:IconFile")
+                        .build()?,
+                    )
                     .build()?,
             )
             .build()?,
@@ -109,37 +177,35 @@ pub fn grant_test(_: Json<GrantActionParams>) -> FnResult<Json<Vec<GrantResource
     };
 
     if let Ok(file_str) = file_id {
-        let mut result: Vec<GrantResource> = Vec::new();
-        result.push(GrantResource::Account {
-            id: String::from("account_test"),
-            user_id: Some(String::from("account_test")),
-            username: Some(String::from("Test Guy")),
-            email: Some(String::from("test@test.test")),
-            password: Some(HashedPassword::new("testpw")),
-            metadata: None,
-        });
-
-        result.push(GrantResource::File {
-            id: String::from("file_test"),
-            file_id: file_str,
-            filename: Some(String::from("text_file.txt")),
-            content_type: Some(String::from("text/plain")),
-            metadata: None,
-        });
-
-        result.push(GrantResource::Url {
-            id: String::from("url_test"),
-            url: String::from("https://github.com/dax-dot-gay/Invex"),
-            alias: None,
-            label: Some(String::from("Cool Link!")),
-            metadata: None,
-        });
-
-        result.push(GrantResource::Generic {
-            id: String::from("generic_test"),
-            name: String::from("A Generic Test"),
-            metadata: None,
-        });
+        let result = vec![
+            GrantResource::Account {
+                id: String::from("account_test"),
+                user_id: Some(String::from("account_test")),
+                username: Some(String::from("Test Guy")),
+                email: Some(String::from("test@test.test")),
+                password: Some(HashedPassword::new("testpw")),
+                metadata: None,
+            },
+            GrantResource::File {
+                id: String::from("file_test"),
+                file_id: file_str,
+                filename: Some(String::from("text_file.txt")),
+                content_type: Some(String::from("text/plain")),
+                metadata: None,
+            },
+            GrantResource::Url {
+                id: String::from("url_test"),
+                url: String::from("https://github.com/dax-dot-gay/Invex"),
+                alias: None,
+                label: Some(String::from("Cool Link!")),
+                metadata: None,
+            },
+            GrantResource::Generic {
+                id: String::from("generic_test"),
+                name: String::from("A Generic Test"),
+                metadata: None,
+            }
+        ];
 
         Ok(Json(result))
     } else {